@@ -3,13 +3,15 @@ import { marked } from "marked";
 import DOMPurify from "dompurify";
 import {listen} from "@tauri-apps/api/event";
 import { Window } from "@tauri-apps/api/window";
+import { invoke } from "@tauri-apps/api/core";
 
 const tabNavigation = document.querySelector('.tab-navigation');
 const tabContent = document.querySelector('.tab-content');
 const datasetWindow = new Window('dataset_mng');
 
-const theme = localStorage.getItem('color-theme');
-document.documentElement.setAttribute('color-theme', theme || 'light');
+invoke('get_settings').then((settings) => {
+    document.documentElement.setAttribute('color-theme', settings.theme);
+});
 
 listen('theme-changed', (event) => {
     document.documentElement.setAttribute('color-theme', event.payload);
@@ -42,40 +44,200 @@ async function createTab(dataset) {
     `;
 
     // Add click handler
-    tabButton.addEventListener('click', () => {
-        // Remove active class from all tabs and contents
-        document.querySelectorAll('.tab-button').forEach(btn => btn.classList.remove('active'));
-        document.querySelectorAll('.dataset-content').forEach(content => content.classList.remove('active'));
-
-        // Add active class to clicked tab and its content
-        tabButton.classList.add('active');
-        content.classList.add('active');
+    tabButton.addEventListener('click', () => activateTab(tabButton, content));
+
+    return { tabButton, content };
+}
+
+function activateTab(tabButton, content) {
+    // Remove active class from all tabs and contents
+    document.querySelectorAll('.tab-button').forEach(btn => btn.classList.remove('active'));
+    document.querySelectorAll('.dataset-content').forEach(content => content.classList.remove('active'));
+
+    // Add active class to clicked tab and its content
+    tabButton.classList.add('active');
+    content.classList.add('active');
+}
+
+// Creates the tab for editing the user dataset (user.csv)
+function createUserTab() {
+    const tabButton = document.createElement('button');
+    tabButton.className = 'tab-button';
+    tabButton.textContent = 'My Aliases';
+
+    const content = document.createElement('div');
+    content.className = 'dataset-content';
+    content.innerHTML = `
+        <div class="dataset-header">
+            <h1>My Aliases</h1>
+        </div>
+        <hr/>
+        <p>Aliases added here are saved to <code>user.csv</code> and can be used right away. Invisible characters can be entered as <code>U+XXXX</code> or <code>\\u{XXXX}</code>.</p>
+        <form class="user-alias-form">
+            <input type="text" name="alias" placeholder="alias" autocomplete="off" required />
+            <input type="text" name="value" placeholder="character or U+XXXX" autocomplete="off" required />
+            <button type="submit">Add</button>
+            <button type="button" class="cancel-edit" hidden>Cancel</button>
+        </form>
+        <p class="user-alias-status"></p>
+        <table class="user-alias-table">
+            <thead><tr><th>Alias</th><th>Character</th><th></th></tr></thead>
+            <tbody></tbody>
+        </table>
+    `;
+
+    const form = content.querySelector('.user-alias-form');
+    const submitBtn = form.querySelector('button[type="submit"]');
+    const cancelBtn = form.querySelector('.cancel-edit');
+    const status = content.querySelector('.user-alias-status');
+    const tbody = content.querySelector('tbody');
+    let editing = null; // Entry being edited, or null when adding
+
+    function setStatus(messages, isError) {
+        status.textContent = messages.join('\n');
+        status.classList.toggle('error', isError);
+    }
+
+    function resetForm() {
+        editing = null;
+        form.reset();
+        submitBtn.textContent = 'Add';
+        cancelBtn.hidden = true;
+    }
+
+    async function refresh() {
+        const entries = await invoke('list_user_aliases');
+        tbody.innerHTML = '';
+        for (const entry of entries) {
+            const row = document.createElement('tr');
+            row.innerHTML = `<td><code></code></td><td></td><td><button class="edit">Edit</button> <button class="delete">Delete</button></td>`;
+            row.querySelector('code').textContent = entry.alias;
+            row.children[1].textContent = entry.value;
+            row.querySelector('.edit').addEventListener('click', () => {
+                editing = entry;
+                form.alias.value = entry.alias;
+                form.value.value = entry.value;
+                submitBtn.textContent = 'Save';
+                cancelBtn.hidden = false;
+            });
+            row.querySelector('.delete').addEventListener('click', async () => {
+                try {
+                    await invoke('delete_user_alias', { alias: entry.alias, value: entry.value });
+                    setStatus([`Deleted ${entry.alias}`], false);
+                } catch (e) {
+                    setStatus([e], true);
+                }
+                resetForm();
+                await refresh();
+            });
+            tbody.appendChild(row);
+        }
+    }
+
+    form.addEventListener('submit', async (e) => {
+        e.preventDefault();
+        const alias = form.alias.value;
+        const value = form.value.value;
+        try {
+            const warnings = editing
+                ? await invoke('edit_user_alias', { oldAlias: editing.alias, oldValue: editing.value, alias, value })
+                : await invoke('add_user_alias', { alias, value });
+            setStatus(warnings.length > 0 ? warnings.map(w => `⚠️ ${w}`) : [`Saved ${alias.trim()}`], false);
+            resetForm();
+            await refresh();
+        } catch (e) {
+            setStatus([e], true);
+        }
     });
+    cancelBtn.addEventListener('click', resetForm);
+
+    tabButton.addEventListener('click', () => activateTab(tabButton, content));
+    refresh();
+    return { tabButton, content };
+}
+
+// Creates the tab for checking all datasets with the dataset linter
+function createValidateTab() {
+    const tabButton = document.createElement('button');
+    tabButton.className = 'tab-button';
+    tabButton.textContent = 'Validate';
+
+    const content = document.createElement('div');
+    content.className = 'dataset-content';
+    content.innerHTML = `
+        <div class="dataset-header">
+            <h1>Validate</h1>
+            <button class="run-lint">Check datasets</button>
+        </div>
+        <hr/>
+        <p>Checks every dataset file, including disabled ones, for mistakes such as duplicated aliases, stray whitespace or characters that look alike.</p>
+        <p class="lint-summary"></p>
+        <table class="lint-table" hidden>
+            <thead><tr><th>Severity</th><th>Rule</th><th>Dataset</th><th>File</th><th>Line</th><th>Message</th></tr></thead>
+            <tbody></tbody>
+        </table>
+    `;
 
+    const runBtn = content.querySelector('.run-lint');
+    const summary = content.querySelector('.lint-summary');
+    const table = content.querySelector('.lint-table');
+    const tbody = content.querySelector('tbody');
+
+    async function run() {
+        runBtn.disabled = true;
+        summary.classList.remove('error');
+        try {
+            const issues = await invoke('lint_datasets');
+            const errors = issues.filter(issue => issue.severity === 'error').length;
+            summary.textContent = issues.length === 0
+                ? 'No issues found'
+                : `${issues.length} issue(s), ${errors} error(s)`;
+            tbody.innerHTML = '';
+            for (const issue of issues) {
+                const row = document.createElement('tr');
+                row.className = `lint-${issue.severity}`;
+                for (const text of [issue.severity, issue.rule, issue.dataset, issue.file, issue.line, issue.message]) {
+                    const cell = document.createElement('td');
+                    cell.textContent = text;
+                    row.appendChild(cell);
+                }
+                tbody.appendChild(row);
+            }
+            table.hidden = issues.length === 0;
+        } catch (e) {
+            summary.textContent = e;
+            summary.classList.add('error');
+        }
+        runBtn.disabled = false;
+    }
+
+    runBtn.addEventListener('click', run);
+    tabButton.addEventListener('click', () => activateTab(tabButton, content));
     return { tabButton, content };
 }
 
 async function load_datasets() {
     let datasets = (await readDir("dataset", { baseDir: BaseDirectory.AppData }))
         .filter(entry => entry.name?.endsWith('.csv') && !entry.children)
-        .map(entry => entry.name.slice(0, -4));
+        .map(entry => entry.name.slice(0, -4))
+        .filter(name => name !== 'user'); // The user dataset has its own tab
     console.log("Datasets found:", datasets);
 
     tabNavigation.innerHTML = '';
     tabContent.innerHTML = '';
 
-    if (datasets.length === 0) {
-        tabContent.innerHTML = '<p class="empty-state">No datasets loaded</p>';
-    }
-    else {
-        for (const dataset of datasets) {
-            const { tabButton, content } = await createTab(dataset);
-            tabNavigation.appendChild(tabButton);
-            tabContent.appendChild(content);
-        };
-        tabContent.children[0].classList.add('active'); // Activate the first tab by default
-        tabNavigation.children[0].classList.add('active'); // Activate the first tab button by default
+    for (const dataset of datasets) {
+        const { tabButton, content } = await createTab(dataset);
+        tabNavigation.appendChild(tabButton);
+        tabContent.appendChild(content);
+    };
+    for (const { tabButton, content } of [createUserTab(), createValidateTab()]) {
+        tabNavigation.appendChild(tabButton);
+        tabContent.appendChild(content);
     }
+
+    tabContent.children[0].classList.add('active'); // Activate the first tab by default
+    tabNavigation.children[0].classList.add('active'); // Activate the first tab button by default
 }
 
 window.addEventListener('keydown', (e) => {
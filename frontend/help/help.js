@@ -1,10 +1,12 @@
 import {listen} from "@tauri-apps/api/event";
 import { Window } from "@tauri-apps/api/window";
+import { invoke } from "@tauri-apps/api/core";
 
 const helpWindow = new Window('help');
 
-const theme = localStorage.getItem('color-theme');
-document.documentElement.setAttribute('color-theme', theme || 'light');
+invoke('get_settings').then((settings) => {
+    document.documentElement.setAttribute('color-theme', settings.theme);
+});
 
 listen('theme-changed', (event) => {
     document.documentElement.setAttribute('color-theme', event.payload);
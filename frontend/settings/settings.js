@@ -1,58 +1,253 @@
-import { readTextFile, writeTextFile, BaseDirectory  } from '@tauri-apps/plugin-fs';
+import { invoke } from '@tauri-apps/api/core';
 import { Window } from '@tauri-apps/api/window';
-import { listen, emit } from '@tauri-apps/api/event';
+import { emit } from '@tauri-apps/api/event';
 
 const settingsWindow = new Window('settings');
-const hotkeyInput = document.getElementById('hotkey');
+const hotkeyList = document.getElementById('hotkeyList');
+const addHotkeyBtn = document.getElementById('addHotkeyBtn');
 const saveBtn = document.getElementById('saveBtn');
 const cancelBtn = document.getElementById('cancelBtn');
 const themeSelect = document.getElementById('theme');
+const matchingSelect = document.getElementById('matching');
+const orderSelect = document.getElementById('order');
+const suggestCheckbox = document.getElementById('suggestCorrections');
+const maxResultsInput = document.getElementById('maxResults');
+const insertMethodSelect = document.getElementById('insertMethod');
+const datasetList = document.getElementById('datasetList');
+const autostartCheckbox = document.getElementById('autostart');
+const errorStrip = document.getElementById('settingsError');
 
-// Default settings
-const defaultSettings = {
-    hotkey: 'alt+shift+u'
-};
+let settings = null;
+let savedTheme = null;
+let datasets = [];
+let autostart = false;
 
-// Load settings from file
+function showError(message) {
+    errorStrip.textContent = message;
+    errorStrip.hidden = !message;
+}
+
+function createOption(value, text) {
+    const option = document.createElement('option');
+    option.value = value;
+    option.textContent = text;
+    return option;
+}
+
+// Adds a row editing a hotkey binding ({ hotkey, action })
+function addHotkeyRow(binding) {
+    const row = document.createElement('div');
+    row.className = 'hotkey-row';
+    row.innerHTML = `
+        <input type="text" class="hotkey-input" placeholder="alt+shift+u">
+        <select class="theme-select action-select">
+            <option value="popup">Open popup</option>
+            <option value="repeat">Insert last character</option>
+            <option value="alias">Insert alias</option>
+        </select>
+        <span class="action-options popup-options">
+            <select class="theme-select dataset-select"></select>
+            <select class="theme-select matching-select">
+                <option value="">Default matching</option>
+                <option value="exact">Exact</option>
+                <option value="normalized">Ignore case and separators</option>
+            </select>
+        </span>
+        <span class="action-options alias-options">
+            <input type="text" class="alias-input" placeholder="alias">
+            <input type="number" class="index-input" min="0" value="0" title="Candidate index">
+        </span>
+        <button type="button" class="remove" title="Remove">✕</button>
+    `;
+    const action = binding.action;
+    const datasetSelect = row.querySelector('.dataset-select');
+    datasetSelect.appendChild(createOption('', 'All datasets'));
+    for (const name of datasets) {
+        datasetSelect.appendChild(createOption(name, name));
+    }
+    const actionSelect = row.querySelector('.action-select');
+    const updateOptions = () => {
+        row.querySelector('.popup-options').hidden = actionSelect.value !== 'popup';
+        row.querySelector('.alias-options').hidden = actionSelect.value !== 'alias';
+    };
+
+    row.querySelector('.hotkey-input').value = binding.hotkey;
+    actionSelect.value = action.kind;
+    datasetSelect.value = action.dataset || '';
+    row.querySelector('.matching-select').value = action.matching || '';
+    row.querySelector('.alias-input').value = action.alias || '';
+    row.querySelector('.index-input').value = action.index || 0;
+    updateOptions();
+
+    actionSelect.addEventListener('change', updateOptions);
+    row.querySelector('.remove').addEventListener('click', () => row.remove());
+    hotkeyList.appendChild(row);
+}
+
+// Reads the hotkey bindings back from the rows
+function readHotkeyRows() {
+    return [...hotkeyList.querySelectorAll('.hotkey-row')].map(row => {
+        const kind = row.querySelector('.action-select').value;
+        let action = { kind };
+        if (kind === 'popup') {
+            action.dataset = row.querySelector('.dataset-select').value || null;
+            action.matching = row.querySelector('.matching-select').value || null;
+        } else if (kind === 'alias') {
+            action.alias = row.querySelector('.alias-input').value.trim();
+            action.index = parseInt(row.querySelector('.index-input').value, 10) || 0;
+        }
+        return { hotkey: row.querySelector('.hotkey-input').value.trim(), action };
+    });
+}
+
+// Adds a row enabling a dataset, with a button moving it up in the priority order
+function addDatasetRow(name) {
+    const row = document.createElement('div');
+    row.className = 'dataset-row';
+    const label = document.createElement('label');
+    label.className = 'checkbox-label';
+    const checkbox = document.createElement('input');
+    checkbox.type = 'checkbox';
+    checkbox.value = name;
+    checkbox.checked = !settings.disabled_datasets.includes(name);
+    label.append(checkbox, ` ${name}`);
+    const up = document.createElement('button');
+    up.type = 'button';
+    up.className = 'move';
+    up.title = 'Move up';
+    up.textContent = '▲';
+    up.addEventListener('click', () => {
+        if (row.previousElementSibling) {
+            datasetList.insertBefore(row, row.previousElementSibling);
+        }
+    });
+    row.append(up, label);
+    datasetList.appendChild(row);
+}
+
+function applyTheme(theme) {
+    document.documentElement.setAttribute('color-theme', theme);
+    emit('theme-changed', theme);
+}
+
+// Load settings from the backend
 async function loadSettings() {
-    try {
-        let contents = await readTextFile('settings.json',  { baseDir: BaseDirectory.AppData })
-        let settings = JSON.parse(contents);
+    settings = await invoke('get_settings');
+    savedTheme = settings.theme;
+    document.documentElement.setAttribute('color-theme', settings.theme);
 
-        const theme = localStorage.getItem('color-theme') || 'light';
-        document.documentElement.setAttribute('color-theme', theme);
+    const loadError = await invoke('get_settings_error');
+    if (loadError) {
+        showError(`The settings file could not be loaded, so the defaults are used. Saving will overwrite it.\n${loadError}`);
+    }
+    const hotkeyError = await invoke('get_hotkey_error');
+    if (hotkeyError) {
+        showError([errorStrip.textContent, hotkeyError].filter(Boolean).join('\n'));
+    }
 
-        hotkeyInput.value = settings.hotkey || defaultSettings.hotkey;
+    matchingSelect.value = settings.matching;
+    orderSelect.value = settings.order;
+    suggestCheckbox.checked = settings.suggest_corrections;
+    maxResultsInput.value = settings.max_results;
+    insertMethodSelect.value = settings.insert_method;
+    themeSelect.value = settings.theme;
+
+    try {
+        autostart = await invoke('get_autostart');
+        autostartCheckbox.checked = autostart;
     } catch (error) {
-        console.error('Error loading settings:', error);
-        hotkeyInput.value = defaultSettings.hotkey;
+        console.error('Failed to read autostart:', error);
+        autostartCheckbox.disabled = true;
+    }
+
+    try {
+        datasets = await invoke('list_datasets');
+        // List the datasets in order of priority, unlisted ones last
+        const rank = name => {
+            const pos = settings.dataset_priority.indexOf(name);
+            return pos < 0 ? settings.dataset_priority.length : pos;
+        };
+        const ordered = [...datasets].sort((a, b) => rank(a) - rank(b));
+        datasetList.innerHTML = '';
+        for (const name of ordered) {
+            addDatasetRow(name);
+        }
+    } catch (error) {
+        console.error('Failed to list datasets:', error);
+    }
+
+    hotkeyList.innerHTML = '';
+    for (const binding of settings.hotkeys) {
+        addHotkeyRow(binding);
     }
 }
 
-// Save settings to file
+// Validate and save settings through the backend
 async function saveSettings() {
+    const listed = [...datasetList.querySelectorAll('input[type="checkbox"]')];
+    const newSettings = {
+        ...settings,
+        hotkeys: readHotkeyRows(),
+        matching: matchingSelect.value,
+        order: orderSelect.value,
+        suggest_corrections: suggestCheckbox.checked,
+        // Keep the priority of datasets that are no longer listed
+        dataset_priority: listed.map(cb => cb.value)
+            .concat(settings.dataset_priority.filter(name => !listed.some(cb => cb.value === name))),
+        max_results: parseInt(maxResultsInput.value, 10) || 0,
+        insert_method: insertMethodSelect.value,
+        theme: themeSelect.value,
+        // Keep datasets that are disabled but no longer listed
+        disabled_datasets: settings.disabled_datasets
+            .filter(name => !listed.some(cb => cb.value === name))
+            .concat(listed.filter(cb => !cb.checked).map(cb => cb.value))
+    };
+    if (!autostartCheckbox.disabled && autostartCheckbox.checked !== autostart) {
+        try {
+            await invoke('set_autostart', { enabled: autostartCheckbox.checked });
+            autostart = autostartCheckbox.checked;
+        } catch (error) {
+            console.error('Failed to update autostart:', error);
+            showError(error);
+            return;
+        }
+    }
     try {
-        const settings = {
-            hotkey: hotkeyInput.value
-        };
-        await writeTextFile('settings.json', JSON.stringify(settings, null, 2),  { baseDir: BaseDirectory.AppData });
-        localStorage.setItem('color-theme', themeSelect.value);
-        settingsWindow.close();
+        await invoke('save_settings', { settings: newSettings });
     } catch (error) {
         console.error('Failed to save settings:', error);
+        showError(error);
+        return;
     }
+    savedTheme = newSettings.theme;
+    const datasetsChanged = JSON.stringify(newSettings.disabled_datasets) !== JSON.stringify(settings.disabled_datasets);
+    if (datasetsChanged) {
+        try {
+            await invoke('load_dataset');
+        } catch (error) {
+            console.error('Failed to reload datasets:', error);
+        }
+    }
+    settingsWindow.close();
+}
+
+function cancel() {
+    // Revert the theme preview
+    if (savedTheme && themeSelect.value !== savedTheme) {
+        applyTheme(savedTheme);
+    }
+    settingsWindow.close();
 }
 
 // Event listeners
 saveBtn.addEventListener('click', saveSettings);
-cancelBtn.addEventListener('click', () => {
-    settingsWindow.close();
-});
+addHotkeyBtn.addEventListener('click', () => addHotkeyRow({ hotkey: '', action: { kind: 'popup' } }));
+cancelBtn.addEventListener('click', cancel);
 
 window.addEventListener('keydown', (e) => {
   if (e.key === "Escape") {
     e.preventDefault();
-    settingsWindow.close();
+    cancel();
     return;
   }}
 )
@@ -60,9 +255,6 @@ window.addEventListener('keydown', (e) => {
 // Load settings when window opens
 loadSettings();
 
-themeSelect.value = localStorage.getItem('color-theme') || 'light';
 themeSelect.addEventListener('change', (e) => {
-    const theme = e.target.value;
-    document.documentElement.setAttribute('color-theme', theme);
-    emit('theme-changed', theme);
-});
\ No newline at end of file
+    applyTheme(e.target.value);
+});
@@ -28,6 +28,8 @@ const window_config = {
 
 const appWindow = new Window('main');
 const compList = document.getElementById('autocompleteList');
+const detailPanel = document.getElementById('detailPanel');
+const matchNotice = document.getElementById('matchNotice');
 const txtInput = document.getElementById('textInput');
 const helpBtn = document.getElementById('helpButton');
 const reloadBtn = document.getElementById('reloadButton');
@@ -35,10 +37,15 @@ const settingsBtn = document.getElementById('settingsButton');
 const datasetBtn = document.getElementById('datasetButton');
 
 let childnum = -1;
+let matchInput = ''; // Input of the matches currently shown
+let matchTotal = 0; // Number of all matches for matchInput
+let scope = {}; // Dataset and matching mode set by the hotkey that opened the window
 
 txtInput.focus()
 
 listen('show_window', (event) => {
+  scope = event.payload || {};
+  txtInput.placeholder = scope.dataset ? `Type alias in ${scope.dataset}...` : 'Type alias...';
   appWindow.show(); // Show the window when the event is received
   const setFocus = async () => {
     if (await appWindow.isMinimized()) {
@@ -46,6 +53,7 @@ listen('show_window', (event) => {
     }
     await appWindow.setFocus()
     txtInput.focus();
+    find_matches(txtInput.value); // Show the favorites
   };
   setFocus();
 });
@@ -69,62 +77,242 @@ function createWindow(label) {
   return newWindow;
 }
 
+// Opens a window on request of the tray menu, or focuses it if it is already open
+listen('open_window', async (event) => {
+  const existing = await WebviewWindow.getByLabel(event.payload);
+  if (existing) {
+    await existing.setFocus();
+  } else {
+    createWindow(event.payload);
+  }
+});
+
+// Creates an element with the given text. Aliases and values come from dataset files, so they are never parsed as HTML
+function text_element(tag, text, className) {
+  const element = document.createElement(tag);
+  element.textContent = text;
+  if (className) {
+    element.className = className;
+  }
+  return element;
+}
+
+function create_item(match) {
+  const item = document.createElement('span');
+  const matchend = match.matchstart + match.matchlen;
+  if (match.favorite !== null) {
+    item.append('★ ');
+  }
+  const character = text_element('span', match.value, 'character-span');
+  if (match.matchstr.length === 0) {
+    // Raw character favorite
+    item.append(character);
+  } else {
+    item.append(
+      match.matchstr.slice(0, match.matchstart),
+      text_element('strong', match.matchstr.slice(match.matchstart, matchend)),
+      match.matchstr.slice(matchend),
+      ' (', character, ')'
+    );
+  }
+  if (match.description) {
+    const description = document.createElement('span');
+    description.className = 'description-span';
+    description.textContent = match.description; // Set as text, descriptions come from dataset files
+    item.appendChild(description);
+    item.dataset.description = match.description;
+  }
+  item.className = 'autocomplete-item';
+  item.dataset.alias = match.matchstr;
+  item.dataset.index = match.index;
+  item.dataset.value = match.value;
+  if (match.favorite !== null) {
+    item.dataset.favorite = match.favorite;
+  }
+  item.addEventListener('click', async () => {
+    await clear_and_hide(); // Clear input and hide the window
+    await insert_item(item);
+  });
+  return item;
+}
+
+// Inputs the character of an item
+async function insert_item(item) {
+  if (item.dataset.alias.length === 0) {
+    return await invoke("select_favorite", { position: Number(item.dataset.favorite) });
+  }
+  return await select_alias(item.dataset.alias, Number(item.dataset.index));
+}
+
+// Adds the selected item to the favorites, or removes it if it already is one
+// If there is no item and the input is a single non-ASCII character, the character is added instead
+async function toggle_favorite() {
+  let favorite;
+  const item = compList.children[childnum];
+  if (item) {
+    favorite = item.dataset.alias.length === 0
+      ? { kind: 'char', value: item.dataset.value }
+      : { kind: 'alias', alias: item.dataset.alias, value: item.dataset.value };
+  } else if ([...txtInput.value].length === 1 && !/^[\x00-\x7F]$/.test(txtInput.value)) {
+    favorite = { kind: 'char', value: txtInput.value };
+  } else {
+    return;
+  }
+  try {
+    if (item && item.dataset.favorite !== undefined) {
+      await invoke("remove_favorite", { favorite });
+    } else {
+      await invoke("add_favorite", { favorite });
+    }
+  }
+  catch (e) {
+    error(`Error updating favorites: ${e}`);
+  }
+  await find_matches(txtInput.value);
+}
 
 async function find_matches(text) {
   // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-  let matches = await invoke("find_matches", { input: text, cnt: 5 });
-  //console.log(matches)
+  // The number of matches per page is given by the settings
+  // An empty text lists the favorites
+  let page = await invoke("find_matches", { input: text, dataset: scope.dataset, matching: scope.matching });
+  //console.log(page)
+  matchInput = text;
+  matchTotal = page.total;
+  show_notice(page.matches.length > 0 ? page.kind : 'full');
   compList.innerHTML = ''; // Clear previous results
-  matches.forEach(match => {
-    const item = document.createElement('span');
-    item.innerHTML = `<strong>${match.matchstr.slice(0, match.matchlen)}</strong>${match.matchstr.slice(match.matchlen, match.matchstr.length)} (<span class="character-span">${match.value}</span>)`;
-    item.className = 'autocomplete-item';
-    item.dataset.alias = match.matchstr;
-    item.addEventListener('click', async () => {
-      await clear_and_hide(); // Clear input and hide the window
-      await select_alias(match.matchstr);
-    });
-    compList.appendChild(item);
-  });
-  if (matches.length > 0) {
+  page.matches.forEach(match => compList.appendChild(create_item(match)));
+  if (page.matches.length > 0) {
     compList.children[0].classList.add('selected'); // Select the first item by default
     childnum = 0; // Reset childnum to the first item
+    show_details(compList.children[0]);
   } else {
     childnum = -1; // No matches found
+    detailPanel.innerHTML = '';
   }
 }
 
-async function select_alias(alias) {
-  return await invoke("select_alias", { alias });
+// Tells when no alias starts with the whole input
+function show_notice(kind) {
+  if (kind === 'corrected') {
+    matchNotice.textContent = 'No alias starts with this. Did you mean:';
+  } else if (kind === 'description') {
+    matchNotice.textContent = 'No alias starts with this. Matching descriptions:';
+  } else if (kind === 'partial') {
+    matchNotice.textContent = 'No alias starts with this. Closest matches:';
+  } else {
+    matchNotice.textContent = '';
+  }
+  matchNotice.hidden = !matchNotice.textContent;
+}
+
+// Shows the Unicode properties of the candidate of an item in the detail panel
+async function show_details(item) {
+  if (item.dataset.alias.length === 0) {
+    detailPanel.innerHTML = ''; // Raw character favorites have no alias
+    return;
+  }
+  let details;
+  try {
+    details = await invoke("get_candidate_details", { alias: item.dataset.alias, index: Number(item.dataset.index) });
+  }
+  catch (e) {
+    error(`Error getting candidate details: ${e}`);
+    detailPanel.innerHTML = '';
+    return;
+  }
+  if (compList.children[childnum] !== item) {
+    return; // Selection changed while loading
+  }
+  const info = details.info;
+  const dl = document.createElement('dl');
+  const rows = [
+    ['Codepoint', info.codepoint],
+    ['Category', `${info.category_name} (${info.category})`],
+    ['Block', info.block ?? '(none)'],
+    ['Script', info.script],
+    ['Dataset', details.dataset],
+  ];
+  if (item.dataset.description) {
+    rows.unshift(['Description', item.dataset.description]);
+  }
+  for (const [term, text] of rows) {
+    dl.append(text_element('dt', term), text_element('dd', text));
+  }
+  detailPanel.replaceChildren(
+    text_element('div', details.value, 'detail-char'),
+    text_element('div', info.name ?? '(no name)', 'detail-name'),
+    dl
+  );
+}
+
+// Appends the next page of matches to the list
+async function load_more_matches() {
+  const text = matchInput;
+  let page = await invoke("find_matches", { input: text, offset: compList.children.length, dataset: scope.dataset, matching: scope.matching });
+  if (text !== matchInput) {
+    return; // Input changed while loading
+  }
+  page.matches.forEach(match => compList.appendChild(create_item(match)));
+}
+
+function select_item(num) {
+  if (childnum >= 0 && childnum < compList.children.length) {
+    compList.children[childnum].classList.remove('selected'); // Remove selection from current item
+  }
+  childnum = num;
+  console.log(`Selected item index: ${childnum}`);
+  compList.children[childnum].classList.add('selected');
+  compList.children[childnum].scrollIntoView({ block: 'nearest' });
+  show_details(compList.children[childnum]);
+}
+
+async function select_alias(alias, index) {
+  return await invoke("select_alias", { alias, index });
 }
 
 async function clear_and_hide() {
   txtInput.value = ''; // Clear the input field
   compList.innerHTML = ''; // Clear the list
+  detailPanel.innerHTML = '';
+  show_notice('full');
+  scope = {};
+  txtInput.placeholder = 'Type alias...';
   await appWindow.hide(); // Hide the window
 }
 
 window.onload = async () => {
-  try {
-    await invoke("load_dataset");
+  // Datasets are loaded in the background on startup, the matches are refreshed when done
+  await find_matches(txtInput.value); // Show the favorites
+}
+
+listen('dataset_progress', (event) => {
+  const p = event.payload;
+  reloadBtn.disabled = true;
+  reloadBtn.title = `Loading datasets... (${p.files_done}/${p.files_total} files, ${p.entries} entries)`;
+  for (const w of p.warnings) {
+    console.warn(`${p.file}: ${w}`);
   }
-  catch (e) {
-    error(`Error loading dataset on window load: ${e}`);
+});
+
+listen('dataset_loaded', (event) => {
+  const result = event.payload;
+  reloadBtn.disabled = false;
+  reloadBtn.title = 'Reload alias dataset';
+  if (result.error) {
+    error(`Error loading dataset: ${result.error}`);
   }
-}
+  find_matches(txtInput.value); // Refresh the matches with the new datasets
+});
 
 txtInput.addEventListener("input", (event) => {
-  if (event.target.value.length === 0) {
-    compList.innerHTML = ''; // Clear results if input is less than 3 characters
-    return;
-  }
-  find_matches(event.target.value);
+  find_matches(event.target.value); // Lists the favorites if the input is empty
   childnum = -1; // Reset childnum when input changes
 });
 
 reloadBtn.addEventListener('click', async () => {
   try {
-    await invoke("load_dataset");
+    await invoke("load_dataset"); // Returns immediately, see 'dataset_loaded'
   }
   catch (e) {
     error(`Error reloading dataset: ${e}`);
@@ -143,11 +331,16 @@ window.addEventListener('keydown', (e) => {
   if (e.key === 'Enter' && childnum >= 0 && childnum < compList.children.length) {
     e.preventDefault();
     //close the window and send api
-    let alias = compList.children[childnum].dataset.alias;
+    let item = compList.children[childnum];
     clear_and_hide().then(() => {
-      select_alias(alias)
+      insert_item(item)
     });
   }
+  if (e.key === 's' && e.ctrlKey) {
+    e.preventDefault();
+    toggle_favorite();
+    return;
+  }
   if (e.key === "Tab") {
     txtInput.focus(); // Ensure the input field is focused
   }
@@ -157,26 +350,19 @@ window.addEventListener('keydown', (e) => {
     if (compList.children.length === 0)
       return; // No items to select
 
-    // if (childnum >= 0) {
-    compList.children[childnum].classList.remove('selected'); // Remove selection from current item
-    // }
-
-    childnum = (childnum + 1) % compList.children.length; // Cycle through items
-    console.log(`Selected item index: ${childnum}`);
-    compList.children[childnum].classList.add('selected');
+    if (childnum === compList.children.length - 1 && compList.children.length < matchTotal) {
+      // Load the next page before moving past the last item
+      load_more_matches().then(() => select_item((childnum + 1) % compList.children.length));
+      return;
+    }
+    select_item((childnum + 1) % compList.children.length); // Cycle through items
   }
   else if (e.key === 'ArrowUp') {
     e.preventDefault(); // Prevent default tab behavior
     if (compList.children.length === 0)
       return; // No items to select
 
-    // if (childnum >= 0) {
-    compList.children[childnum].classList.remove('selected'); // Remove selection from current item
-    // }
-
-    childnum = (childnum - 1 + compList.children.length) % compList.children.length; // Cycle through items
-    console.log(`Selected item index: ${childnum}`);
-    compList.children[childnum].classList.add('selected');
+    select_item((childnum - 1 + compList.children.length) % compList.children.length); // Cycle through items
   }
 }
 );
@@ -194,8 +380,19 @@ datasetBtn.addEventListener('click', () => {
   createWindow('dataset_mng'); // Show the dataset management window
 });
 
-const theme = localStorage.getItem('color-theme');
-document.documentElement.setAttribute('color-theme', theme || 'light');
+invoke('get_settings').then(async (settings) => {
+  // The theme used to be stored in localStorage. Move it into the settings once
+  // (unless the settings file failed to load, so that it is not overwritten)
+  const legacyTheme = localStorage.getItem('color-theme');
+  if (legacyTheme && !(await invoke('get_settings_error'))) {
+    localStorage.removeItem('color-theme');
+    if (legacyTheme !== settings.theme) {
+      settings.theme = legacyTheme;
+      await invoke('save_settings', { settings }).catch((e) => error(`Failed to migrate theme: ${e}`));
+    }
+  }
+  document.documentElement.setAttribute('color-theme', settings.theme);
+});
 
 listen('theme-changed', (event) => {
   document.documentElement.setAttribute('color-theme', event.payload);
@@ -0,0 +1,302 @@
+//! Benchmarks of the trie and matching, on the bundled `math_unicode.csv` and on a synthetic 100k-alias dataset
+//!
+//! Run with `cargo bench --bench trie`. Compare a redesign against a baseline with
+//! `cargo bench --bench trie -- --save-baseline before` and then `cargo bench --bench trie -- --baseline before`
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use std::fs;
+use std::path::PathBuf;
+use unialias_core::dataset::{self, DatasetLine};
+use unialias_core::favorites::Favorites;
+use unialias_core::matching;
+use unialias_core::settings::{MatchMode, MatchOrder};
+use unialias_core::trie::Trie;
+use unialias_core::usage::Usage;
+
+const SYNTHETIC_ALIASES: usize = 100_000;
+const PREFIX_LENGTHS: [usize; 4] = [1, 2, 4, 8];
+/// Number of queries per prefix length
+const QUERIES: usize = 1000;
+/// Number of matches kept by `find_matches` (the default page size)
+const MATCH_LIMIT: usize = 5;
+
+struct Dataset {
+    name: &'static str,
+    path: PathBuf,
+    entries: Vec<(String, char)>,
+}
+
+fn bundled_dataset() -> Dataset {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../dataset/math_unicode.csv");
+    let entries = fs::read_to_string(&path)
+        .expect("Failed to read the bundled dataset")
+        .lines()
+        .filter_map(|line| match dataset::parse_line(line) {
+            Ok(DatasetLine::Entry { alias, value, .. }) => Some((alias, value)),
+            _ => None,
+        })
+        .collect();
+    Dataset {
+        name: "math_unicode",
+        path,
+        entries,
+    }
+}
+
+// Generates aliases of 2 to 12 lowercase letters, sometimes with a separator or a digit,
+// mapped to characters of the symbol blocks. The same seed always gives the same dataset
+fn synthetic_dataset() -> Dataset {
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut next = move |bound: u64| {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state % bound
+    };
+    let entries: Vec<(String, char)> = (0..SYNTHETIC_ALIASES)
+        .map(|_| {
+            let len = 2 + next(11) as usize;
+            let alias: String = (0..len)
+                .map(|i| match next(20) {
+                    0 if i > 0 => '_',
+                    1 => char::from(b'0' + next(10) as u8),
+                    _ => char::from(b'a' + next(26) as u8),
+                })
+                .collect();
+            let value = char::from_u32(0x2100 + next(0x0B00) as u32).unwrap();
+            (alias, value)
+        })
+        .collect();
+
+    let path = std::env::temp_dir().join("unialias_bench_synthetic.csv");
+    let csv: String = entries
+        .iter()
+        .map(|(alias, value)| format!("{},{}\n", alias, value))
+        .collect();
+    fs::write(&path, csv).expect("Failed to write the synthetic dataset");
+    Dataset {
+        name: "synthetic_100k",
+        path,
+        entries,
+    }
+}
+
+fn build_trie(entries: &[(String, char)]) -> Trie {
+    let mut trie = Trie::new();
+    let dataset = trie.add_dataset("bench");
+    for (alias, value) in entries {
+        let _ = trie.append_leaf(alias.clone(), *value, dataset);
+    }
+    trie.build_infix_index();
+    trie
+}
+
+// Prefixes of the given length, taken from aliases spread over the dataset
+fn queries(entries: &[(String, char)], len: usize) -> Vec<String> {
+    let step = (entries.len() / QUERIES).max(1);
+    entries
+        .iter()
+        .step_by(step)
+        .filter(|(alias, _)| alias.len() >= len)
+        .map(|(alias, _)| alias[..len].to_string())
+        .take(QUERIES)
+        .collect()
+}
+
+fn bench_append_leaf(c: &mut Criterion, datasets: &[Dataset]) {
+    let mut group = c.benchmark_group("append_leaf");
+    group.sample_size(20);
+    for d in datasets {
+        group.throughput(Throughput::Elements(d.entries.len() as u64));
+        group.bench_function(d.name, |b| {
+            b.iter_batched(
+                || d.entries.clone(),
+                |entries| {
+                    let mut trie = Trie::new();
+                    let dataset = trie.add_dataset("bench");
+                    for (alias, value) in entries {
+                        let _ = trie.append_leaf(alias, value, dataset);
+                    }
+                    trie
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_find_max_match(c: &mut Criterion, datasets: &[(Dataset, Trie)]) {
+    let mut group = c.benchmark_group("find_max_match");
+    for (d, trie) in datasets {
+        for len in PREFIX_LENGTHS {
+            let queries = queries(&d.entries, len);
+            group.throughput(Throughput::Elements(queries.len() as u64));
+            group.bench_with_input(BenchmarkId::new(d.name, len), &queries, |b, queries| {
+                b.iter(|| {
+                    for q in queries {
+                        black_box(trie.find_max_match(q.as_bytes()));
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_find_matches(c: &mut Criterion, datasets: &[(Dataset, Trie)]) {
+    let favorites = Favorites::default();
+    let usage = Usage::default();
+    for (mode, order, group_name) in [
+        (MatchMode::Exact, MatchOrder::Score, "find_matches/exact"),
+        (
+            MatchMode::Normalized,
+            MatchOrder::Score,
+            "find_matches/normalized",
+        ),
+        (
+            MatchMode::Exact,
+            MatchOrder::Shortest,
+            "find_matches/shortest",
+        ),
+    ] {
+        let mut group = c.benchmark_group(group_name);
+        group.sample_size(20);
+        for (d, trie) in datasets {
+            let ranking = matching::Ranking::new(order, trie, &[], &usage);
+            for len in PREFIX_LENGTHS {
+                // Short prefixes match most of the synthetic dataset, so fewer queries are run
+                let queries: Vec<String> = queries(&d.entries, len).into_iter().take(100).collect();
+                group.throughput(Throughput::Elements(queries.len() as u64));
+                group.bench_with_input(BenchmarkId::new(d.name, len), &queries, |b, queries| {
+                    b.iter(|| {
+                        for q in queries {
+                            black_box(matching::find_matches(
+                                trie,
+                                &favorites,
+                                q,
+                                None,
+                                mode,
+                                &ranking,
+                                MATCH_LIMIT,
+                            ));
+                        }
+                    })
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+fn bench_find_corrections(c: &mut Criterion, datasets: &[(Dataset, Trie)]) {
+    let favorites = Favorites::default();
+    let usage = Usage::default();
+    let mut group = c.benchmark_group("find_corrections");
+    group.sample_size(20);
+    for (d, trie) in datasets {
+        let ranking = matching::Ranking::new(MatchOrder::Score, trie, &[], &usage);
+        for len in [4, 8] {
+            // A typo in the last byte (1 typo allowed for 4 bytes, 2 for 8)
+            let queries: Vec<String> = queries(&d.entries, len)
+                .into_iter()
+                .take(100)
+                .map(|q| format!("{}~", &q[..len - 1]))
+                .collect();
+            group.throughput(Throughput::Elements(queries.len() as u64));
+            group.bench_with_input(BenchmarkId::new(d.name, len), &queries, |b, queries| {
+                b.iter(|| {
+                    for q in queries {
+                        black_box(matching::find_corrections(
+                            trie,
+                            &favorites,
+                            q,
+                            None,
+                            MatchMode::Exact,
+                            &ranking,
+                            MATCH_LIMIT,
+                        ));
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_trie_iter(c: &mut Criterion, datasets: &[(Dataset, Trie)]) {
+    let mut group = c.benchmark_group("trie_iter");
+    for (d, trie) in datasets {
+        group.bench_function(d.name, |b| b.iter(|| black_box(trie.iter(0).count())));
+    }
+    group.finish();
+}
+
+fn bench_load_dataset(c: &mut Criterion, datasets: &[Dataset]) {
+    let mut group = c.benchmark_group("load_dataset");
+    group.sample_size(10);
+    for d in datasets {
+        let files = vec![d.path.clone()];
+        group.bench_function(BenchmarkId::new("parse", d.name), |b| {
+            b.iter(|| dataset::load_datasets(&files, |_, _, _| {}).unwrap())
+        });
+
+        // The same files loaded from the compiled cache
+        let sources: Vec<_> = files
+            .iter()
+            .map(|path| unialias_core::cache::SourceFile::read(path, &[]).unwrap())
+            .collect();
+        let cache_path = std::env::temp_dir().join(format!("unialias_bench_{}.bin", d.name));
+        let cached = unialias_core::cache::CachedDatasets {
+            trie: dataset::load_datasets(&files, |_, _, _| {}).unwrap(),
+            entries: d.entries.len(),
+            warnings: Vec::new(),
+        };
+        unialias_core::cache::save(&cache_path, &sources, &cached).unwrap();
+        group.bench_function(BenchmarkId::new("cache", d.name), |b| {
+            b.iter(|| {
+                unialias_core::cache::load(&cache_path, &files)
+                    .unwrap()
+                    .1
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+// Criterion only measures time, so the memory used by each trie is printed once
+fn report_memory_usage(datasets: &[(Dataset, Trie)]) {
+    for (d, trie) in datasets {
+        println!(
+            "trie memory_usage/{}: {:.2} MB",
+            d.name,
+            trie.memory_usage() as f64 / 1e6
+        );
+    }
+}
+
+fn benches(c: &mut Criterion) {
+    let datasets = vec![bundled_dataset(), synthetic_dataset()];
+    bench_append_leaf(c, &datasets);
+    bench_load_dataset(c, &datasets);
+
+    let tries: Vec<(Dataset, Trie)> = datasets
+        .into_iter()
+        .map(|d| {
+            let trie = build_trie(&d.entries);
+            (d, trie)
+        })
+        .collect();
+    report_memory_usage(&tries);
+    bench_find_max_match(c, &tries);
+    bench_find_matches(c, &tries);
+    bench_find_corrections(c, &tries);
+    bench_trie_iter(c, &tries);
+}
+
+criterion_group!(trie_benches, benches);
+criterion_main!(trie_benches);
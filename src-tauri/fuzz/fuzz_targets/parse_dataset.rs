@@ -0,0 +1,22 @@
+#![no_main]
+
+// Parses arbitrary bytes as a dataset file
+// Parsing may fail, but must not panic, and the trie must stay valid
+
+use libfuzzer_sys::fuzz_target;
+use unialias_core::dataset::parse_dataset;
+use unialias_core::trie::Trie;
+
+fuzz_target!(|data: &[u8]| {
+    let mut trie = Trie::new();
+    let _ = parse_dataset(data, "fuzz", &mut trie);
+    trie.build_infix_index();
+    if let Err(e) = trie.validate() {
+        panic!("Invalid trie after parsing: {}", e);
+    }
+    for (idx, _) in trie.iter(0) {
+        if trie.candidates(idx).is_some() {
+            trie.find_value(trie.value_str(idx)).unwrap();
+        }
+    }
+});
@@ -0,0 +1,109 @@
+#![no_main]
+
+// Runs arbitrary sequences of operations against a trie
+// Operations may fail on invalid input, but must not panic, and the trie must stay valid
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use unialias_core::trie::{Candidate, Trie};
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    AddDataset,
+    Insert {
+        alias: String,
+        value: char,
+        dataset: usize,
+    },
+    InsertIndexed {
+        alias: String,
+        value: char,
+        dataset: usize,
+    },
+    Remove {
+        alias: String,
+        value: char,
+        dataset: usize,
+    },
+    Find(String),
+    MaxMatch(Vec<u8>),
+    Normalized(Vec<u8>),
+    BuildInfixIndex,
+    Infix(Vec<u8>),
+    Iter(usize),
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut trie = Trie::new();
+    for op in ops {
+        match op {
+            Op::AddDataset => {
+                trie.add_dataset("fuzz");
+            }
+            Op::Insert {
+                alias,
+                value,
+                dataset,
+            } => {
+                if trie.append_leaf(alias.clone(), value, dataset).is_ok() {
+                    let found = trie.find_value(&alias).unwrap();
+                    assert!(found
+                        .iter()
+                        .any(|c| c.value == value && c.dataset == dataset));
+                }
+            }
+            Op::InsertIndexed {
+                alias,
+                value,
+                dataset,
+            } => {
+                let candidate = Candidate {
+                    value,
+                    dataset,
+                    description: None,
+                };
+                // A new alias is indexed, an existing one may have been appended without indexing
+                let new = trie.find_value(&alias).is_err();
+                if trie.insert_candidate(alias.clone(), candidate).is_ok() && new {
+                    let infix = alias.get(1..).unwrap_or_default();
+                    let found = trie.find_infix_matches(infix.as_bytes());
+                    assert!(
+                        infix.is_empty()
+                            || found
+                                .iter()
+                                .any(|&(idx, _)| trie.value(idx) == alias.as_bytes())
+                    );
+                }
+            }
+            Op::Remove {
+                alias,
+                value,
+                dataset,
+            } => {
+                let _ = trie.remove_candidate(&alias, value, dataset);
+            }
+            Op::Find(alias) => {
+                let _ = trie.find_value(&alias);
+            }
+            Op::MaxMatch(input) => {
+                let (idx, len) = trie.find_max_match(&input);
+                assert_eq!(&trie.value(idx)[..len], &input[..len]);
+            }
+            Op::Normalized(input) => {
+                trie.find_normalized_matches(&input);
+            }
+            Op::BuildInfixIndex => trie.build_infix_index(),
+            Op::Infix(input) => {
+                trie.find_infix_matches(&input);
+            }
+            Op::Iter(idx) => {
+                // Only existing nodes can be iterated, the root always exists
+                let count = trie.iter(0).count();
+                trie.iter(idx % count).for_each(drop);
+            }
+        }
+        if let Err(e) = trie.validate() {
+            panic!("Invalid trie: {}", e);
+        }
+    }
+});
@@ -0,0 +1,285 @@
+use std::path::PathBuf;
+
+/// Argument given when the app is started at login, so that it starts in the system tray
+pub const HIDDEN_ARG: &str = "--hidden";
+/// Name of the autostart entry. The Windows installer registers the same name (`windows/fragments/startup.wxs`)
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+const ENTRY_NAME: &str = "UniAlias";
+
+/// Returns whether the app is started at login
+pub fn is_enabled() -> anyhow::Result<bool> {
+    imp::is_enabled()
+}
+
+/// Starts the app at login with `--hidden`, or stops doing so
+pub fn set_enabled(enabled: bool) -> anyhow::Result<()> {
+    if enabled {
+        imp::enable(&executable_path()?)
+    } else {
+        imp::disable()
+    }
+}
+
+// Path of the executable to start at login
+fn executable_path() -> anyhow::Result<PathBuf> {
+    // An AppImage runs from a temporary mount, the image itself is given by $APPIMAGE
+    #[cfg(target_os = "linux")]
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+    Ok(std::env::current_exe()?)
+}
+
+// Removes a file, treating a missing file as already removed
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn remove_if_exists(path: &std::path::Path) -> anyhow::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+// XDG autostart entry: ~/.config/autostart/unialias.desktop
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::{remove_if_exists, ENTRY_NAME, HIDDEN_ARG};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn entry_path() -> anyhow::Result<PathBuf> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+            Some(dir) if dir.is_absolute() => dir,
+            _ => std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".config"))
+                .ok_or_else(|| anyhow::anyhow!("Failed to find the home directory"))?,
+        };
+        Ok(config_dir.join("autostart").join("unialias.desktop"))
+    }
+
+    // Quotes an argument of the Exec key, as in the Desktop Entry Specification
+    fn quote_exec_arg(arg: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in arg.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        // Backslashes are escaped again as a string value, and '%' starts a field code
+        quoted.replace('\\', "\\\\").replace('%', "%%")
+    }
+
+    pub fn is_enabled() -> anyhow::Result<bool> {
+        match fs::read_to_string(entry_path()?) {
+            // An entry with Hidden=true is disabled
+            Ok(text) => Ok(!text.lines().any(|l| l.trim() == "Hidden=true")),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn enable(exe: &Path) -> anyhow::Result<()> {
+        let path = entry_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let entry = format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name={}\n\
+             Comment=Quickly insert Unicode characters anywhere by typing aliases!\n\
+             Exec={} {}\n\
+             Terminal=false\n\
+             X-GNOME-Autostart-enabled=true\n",
+            ENTRY_NAME,
+            quote_exec_arg(&exe.to_string_lossy()),
+            HIDDEN_ARG
+        );
+        fs::write(path, entry)?;
+        Ok(())
+    }
+
+    pub fn disable() -> anyhow::Result<()> {
+        remove_if_exists(&entry_path()?)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn exec_args_are_quoted() {
+            assert_eq!(
+                quote_exec_arg("/usr/bin/unialias"),
+                r#""/usr/bin/unialias""#
+            );
+            assert_eq!(
+                quote_exec_arg("/opt/Uni Alias/app"),
+                r#""/opt/Uni Alias/app""#
+            );
+            assert_eq!(quote_exec_arg("/tmp/100%"), r#""/tmp/100%%""#);
+            // Reserved characters are escaped, then the backslashes are escaped again
+            assert_eq!(quote_exec_arg(r#"/a"b"#), r#""/a\\"b""#);
+            assert_eq!(quote_exec_arg("/a`b"), r#""/a\\`b""#);
+            assert_eq!(quote_exec_arg("/$HOME/app"), r#""/\\$HOME/app""#);
+            assert_eq!(quote_exec_arg(r"/a\b"), r#""/a\\\\b""#);
+        }
+
+        // A config directory under the temporary directory, set as XDG_CONFIG_HOME until dropped
+        // Only this module reads XDG_CONFIG_HOME
+        struct TempConfigHome(PathBuf);
+
+        impl TempConfigHome {
+            fn new() -> Self {
+                let path =
+                    std::env::temp_dir().join(format!("unialias-autostart-{}", std::process::id()));
+                let _ = fs::remove_dir_all(&path);
+                std::env::set_var("XDG_CONFIG_HOME", &path);
+                TempConfigHome(path)
+            }
+        }
+
+        impl Drop for TempConfigHome {
+            fn drop(&mut self) {
+                std::env::remove_var("XDG_CONFIG_HOME");
+                let _ = fs::remove_dir_all(&self.0);
+            }
+        }
+
+        #[test]
+        fn enable_and_disable() {
+            let home = TempConfigHome::new();
+            assert!(!is_enabled().unwrap());
+            enable(Path::new("/opt/Uni Alias/unialias")).unwrap();
+            assert!(is_enabled().unwrap());
+            let path = entry_path().unwrap();
+            assert!(path.starts_with(&home.0));
+            let entry = fs::read_to_string(path).unwrap();
+            assert!(entry.contains("\nExec=\"/opt/Uni Alias/unialias\" --hidden\n"));
+            disable().unwrap();
+            assert!(!is_enabled().unwrap());
+            // Disabling twice is fine
+            disable().unwrap();
+        }
+    }
+}
+
+// Value of the Run registry key, the same one the installer writes
+#[cfg(target_os = "windows")]
+mod imp {
+    use super::{ENTRY_NAME, HIDDEN_ARG};
+    use std::io;
+    use std::path::Path;
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+    pub fn is_enabled() -> anyhow::Result<bool> {
+        let (run, _) = RegKey::predef(HKEY_CURRENT_USER).create_subkey(RUN_KEY)?;
+        match run.get_value::<String, _>(ENTRY_NAME) {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn enable(exe: &Path) -> anyhow::Result<()> {
+        let (run, _) = RegKey::predef(HKEY_CURRENT_USER).create_subkey(RUN_KEY)?;
+        run.set_value(
+            ENTRY_NAME,
+            &format!("\"{}\" {}", exe.to_string_lossy(), HIDDEN_ARG),
+        )?;
+        Ok(())
+    }
+
+    pub fn disable() -> anyhow::Result<()> {
+        let (run, _) = RegKey::predef(HKEY_CURRENT_USER).create_subkey(RUN_KEY)?;
+        match run.delete_value(ENTRY_NAME) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Launch agent: ~/Library/LaunchAgents/com.qnd101.unialias.app.plist
+#[cfg(target_os = "macos")]
+mod imp {
+    use super::{remove_if_exists, HIDDEN_ARG};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    const LABEL: &str = "com.qnd101.unialias.app";
+
+    fn agent_path() -> anyhow::Result<PathBuf> {
+        let home = std::env::var_os("HOME")
+            .ok_or_else(|| anyhow::anyhow!("Failed to find the home directory"))?;
+        Ok(PathBuf::from(home)
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", LABEL)))
+    }
+
+    fn escape_xml(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    pub fn is_enabled() -> anyhow::Result<bool> {
+        Ok(agent_path()?.exists())
+    }
+
+    pub fn enable(exe: &Path) -> anyhow::Result<()> {
+        let path = agent_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let plist = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+            LABEL,
+            escape_xml(&exe.to_string_lossy()),
+            HIDDEN_ARG
+        );
+        fs::write(path, plist)?;
+        Ok(())
+    }
+
+    pub fn disable() -> anyhow::Result<()> {
+        remove_if_exists(&agent_path()?)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+mod imp {
+    use std::path::Path;
+
+    pub fn is_enabled() -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    pub fn enable(_exe: &Path) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!(
+            "Autostart is not supported on this platform"
+        ))
+    }
+
+    pub fn disable() -> anyhow::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+//! Checks dataset files for problems (see `unialias_core::lint`)
+//!
+//! Usage: `unialias-lint [--rules] <file or directory>...`
+//! Directories are searched for csv files, which are reported by their path relative to the directory.
+//! Exits with 1 if an error is found, and with 2 if the files cannot be checked
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use unialias_core::lint::{Linter, Rule, Severity};
+
+// Lists the csv files of a directory, sorted
+fn csv_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("csv") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        eprintln!("Usage: unialias-lint [--rules] <file or directory>...");
+        return ExitCode::from(2);
+    }
+    if args.iter().any(|a| a == "--rules") {
+        for rule in Rule::ALL {
+            println!(
+                "{:<14} {:<8} {}",
+                rule.name(),
+                rule.severity().to_string(),
+                rule.description()
+            );
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    // Each file along with the directory it was found in
+    let mut files = Vec::new();
+    for arg in &args {
+        let path = PathBuf::from(arg);
+        if path.is_dir() {
+            match csv_files(&path) {
+                Ok(found) => files.extend(found.into_iter().map(|file| (path.clone(), file))),
+                Err(e) => {
+                    eprintln!("Failed to read {:?}: {}", path, e);
+                    return ExitCode::from(2);
+                }
+            }
+        } else {
+            files.push((PathBuf::new(), path));
+        }
+    }
+
+    let mut linter = Linter::default();
+    for (dir, path) in &files {
+        if let Err(e) = linter.lint_file(dir, path) {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    }
+    let issues = linter.finish();
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .count();
+    eprintln!(
+        "{} file(s) checked, {} issue(s), {} error(s)",
+        files.len(),
+        issues.len(),
+        errors
+    );
+    if errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,353 @@
+use crate::trie::Trie;
+use bincode::Options;
+use memmap2::Mmap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Version of the cache format. Bump it when `Trie` or the cached data changes
+const CACHE_VERSION: u32 = 7;
+const CACHE_MAGIC: [u8; 4] = *b"UATC";
+
+/// A dataset file the cache was built from
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    len: u64,
+    modified: u128, // nanoseconds since the unix epoch
+    hash: [u8; 32], // SHA-256 of the contents
+}
+
+impl SourceFile {
+    /// Reads the size and modification time of the file
+    ///
+    /// The contents are only hashed if they differ from the same file in `previous`, whose hash is reused otherwise
+    pub fn read(path: &Path, previous: &[SourceFile]) -> anyhow::Result<Self> {
+        let meta = fs::metadata(path)?;
+        let modified = meta.modified()?.duration_since(UNIX_EPOCH)?.as_nanos();
+        let unchanged = previous
+            .iter()
+            .find(|s| s.path == path && s.len == meta.len() && s.modified == modified);
+        let hash = match unchanged {
+            Some(s) => s.hash,
+            None => Sha256::digest(fs::read(path)?).into(),
+        };
+        Ok(SourceFile {
+            path: path.to_path_buf(),
+            len: meta.len(),
+            modified,
+            hash,
+        })
+    }
+
+    // The modification time only decides whether to hash the file again
+    fn same_contents(&self, other: &SourceFile) -> bool {
+        self.path == other.path && self.len == other.len && self.hash == other.hash
+    }
+}
+
+// Written first, so that a stale cache is detected without reading the rest of the file
+// It is followed by the statistics and dataset names, then by the arrays of the trie (see `Trie::write_arrays`)
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheHeader {
+    magic: [u8; 4],
+    version: u32,
+    sources: Vec<SourceFile>,
+}
+
+/// A compiled trie along with the statistics of the datasets it was built from
+pub struct CachedDatasets {
+    pub trie: Trie,
+    pub entries: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Loads the cached datasets if the cache was built from the same contents of the given files (in the same order)
+///
+/// The cache is memory-mapped and the trie borrows its arrays, so nothing is copied until the trie is modified.
+/// Also returns the files as they are now, to save a new cache with.
+/// Returns no datasets if there is no cache or it is stale, truncated or corrupted
+pub fn load(
+    path: &Path,
+    files: &[PathBuf],
+) -> anyhow::Result<(Vec<SourceFile>, Option<CachedDatasets>)> {
+    let map = match File::open(path) {
+        // SAFETY: the cache is only replaced by renaming a new file over it (see `save`), never modified in place
+        Ok(file) => Some(unsafe { Mmap::map(&file)? }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    // The header along with its length
+    let header = map.as_deref().and_then(|bytes| {
+        let mut rest = bytes;
+        match read::<CacheHeader>(&mut rest) {
+            Ok(h) if h.magic == CACHE_MAGIC && h.version == CACHE_VERSION => {
+                Some((h, bytes.len() - rest.len()))
+            }
+            _ => None, // written by an incompatible version
+        }
+    });
+    let previous = header.as_ref().map_or(&[][..], |(h, _)| &h.sources);
+    let sources = files
+        .iter()
+        .map(|file| SourceFile::read(file, previous))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let (Some((header, header_len)), Some(map)) = (header, map) else {
+        return Ok((sources, None));
+    };
+    let fresh = header.sources.len() == sources.len()
+        && header
+            .sources
+            .iter()
+            .zip(&sources)
+            .all(|(a, b)| a.same_contents(b));
+    if !fresh {
+        return Ok((sources, None));
+    }
+    let mut rest = &map[header_len..];
+    let Ok((entries, warnings, datasets)) = read(&mut rest) else {
+        return Ok((sources, None)); // truncated, so it gets rebuilt
+    };
+    let offset = (map.len() - rest.len()).next_multiple_of(8);
+    let trie = match Trie::from_mapped(map, offset, datasets) {
+        Ok(trie) if trie.validate().is_ok() => trie,
+        _ => return Ok((sources, None)), // truncated or corrupted, so it gets rebuilt
+    };
+    let cached = CachedDatasets {
+        trie,
+        entries,
+        warnings,
+    };
+    // Store the new modification times, so that touched files are not hashed again on every load
+    // Failing to do so only costs hashing them once more next time
+    if header
+        .sources
+        .iter()
+        .zip(&sources)
+        .any(|(a, b)| a.modified != b.modified)
+    {
+        let _ = save(path, &sources, &cached);
+    }
+    Ok((sources, Some(cached)))
+}
+
+// Deserializes what `bincode::serialize` wrote at the start of the bytes, and skips it
+// The limit keeps a corrupted length from allocating more than the cache holds
+fn read<T: DeserializeOwned>(bytes: &mut &[u8]) -> bincode::Result<T> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(bytes.len() as u64)
+        .deserialize_from(bytes)
+}
+
+/// Writes the cache, replacing the previous one
+pub fn save(path: &Path, sources: &[SourceFile], datasets: &CachedDatasets) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let header = CacheHeader {
+        magic: CACHE_MAGIC,
+        version: CACHE_VERSION,
+        sources: sources.to_vec(),
+    };
+    let stats = (
+        datasets.entries,
+        &datasets.warnings,
+        &datasets.trie.datasets,
+    );
+    let mut bytes = bincode::serialize(&header)?;
+    bincode::serialize_into(&mut bytes, &stats)?;
+    // The arrays of the trie are aligned, so that they can be borrowed from the map
+    bytes.resize(bytes.len().next_multiple_of(8), 0);
+    // Write to a temporary file first, so that a mapped cache is never modified and a half-written one is never read
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    writer.write_all(&bytes)?;
+    datasets.trie.write_arrays(&mut writer)?;
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trie::Candidate;
+    use std::time::{Duration, SystemTime};
+
+    // A directory under the temporary directory, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "unialias-cache-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // Writes a dataset file and a cache built from it. Returns the dataset files and the cache path
+    fn cached(dir: &TempDir) -> (Vec<PathBuf>, PathBuf) {
+        let files = vec![dir.0.join("arrows.csv")];
+        fs::write(&files[0], "rightarrow,→\n").unwrap();
+        let cache = dir.0.join("cache.bin");
+        let mut trie = Trie::new();
+        let dataset = trie.add_dataset("arrows");
+        trie.append_leaf("rightarrow".to_string(), '→', dataset)
+            .unwrap();
+        let candidate = Candidate {
+            value: '⇒',
+            dataset,
+            description: Some(trie.add_description("Double right arrow")),
+        };
+        trie.append_candidate("rightarrow".to_string(), candidate)
+            .unwrap();
+        trie.build_infix_index();
+        let datasets = CachedDatasets {
+            trie,
+            entries: 1,
+            warnings: vec!["a warning".to_string()],
+        };
+        let (sources, _) = load(&cache, &files).unwrap();
+        save(&cache, &sources, &datasets).unwrap();
+        (files, cache)
+    }
+
+    #[test]
+    fn cache_hit() {
+        let dir = TempDir::new("hit");
+        let (files, cache) = cached(&dir);
+        let (sources, datasets) = load(&cache, &files).unwrap();
+        let datasets = datasets.unwrap();
+        assert_eq!(datasets.entries, 1);
+        assert_eq!(datasets.warnings, ["a warning"]);
+        let trie = &datasets.trie;
+        let candidates = trie.find_value("rightarrow").unwrap();
+        assert_eq!(candidates[0].value, '→');
+        assert_eq!(trie.description(&candidates[1]), Some("Double right arrow"));
+        assert_eq!(trie.find_infix_matches(b"arrow").len(), 1);
+        assert_eq!(trie.datasets, ["arrows"]);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].path, files[0]);
+
+        // Touching a file without changing it keeps the cache
+        let later = SystemTime::now() + Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(&files[0])
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(load(&cache, &files).unwrap().1.is_some());
+        assert!(load(&cache, &files).unwrap().1.is_some());
+    }
+
+    #[test]
+    fn changed_sources_invalidate_the_cache() {
+        let dir = TempDir::new("changed");
+        let (files, cache) = cached(&dir);
+        fs::write(&files[0], "leftarrow,←\n").unwrap();
+        assert!(load(&cache, &files).unwrap().1.is_none());
+
+        // Adding or removing a file also makes it stale
+        let (files, cache) = cached(&dir);
+        let other = dir.0.join("other.csv");
+        fs::write(&other, "").unwrap();
+        assert!(load(&cache, &[files[0].clone(), other])
+            .unwrap()
+            .1
+            .is_none());
+        assert!(load(&cache, &[]).unwrap().1.is_none());
+    }
+
+    #[test]
+    fn other_versions_are_ignored() {
+        let dir = TempDir::new("version");
+        let (files, cache) = cached(&dir);
+        let (sources, _) = load(&cache, &files).unwrap();
+        let header = CacheHeader {
+            magic: CACHE_MAGIC,
+            version: CACHE_VERSION + 1,
+            sources,
+        };
+        fs::write(&cache, bincode::serialize(&header).unwrap()).unwrap();
+        assert!(load(&cache, &files).unwrap().1.is_none());
+    }
+
+    #[test]
+    fn loaded_tries_can_be_modified() {
+        let dir = TempDir::new("modified");
+        let (files, cache) = cached(&dir);
+        let mut trie = load(&cache, &files).unwrap().1.unwrap().trie;
+        let candidate = Candidate {
+            value: '↑',
+            dataset: 0,
+            description: Some(trie.add_description("Upwards arrow")),
+        };
+        trie.insert_candidate("uparrow".to_string(), candidate)
+            .unwrap();
+        trie.remove_candidate("rightarrow", '→', 0).unwrap();
+        trie.validate().unwrap();
+        assert_eq!(trie.find_value("rightarrow").unwrap()[0].value, '⇒');
+        assert_eq!(trie.find_description_matches("upwards").len(), 1);
+        assert_eq!(trie.find_infix_matches(b"arrow").len(), 2);
+    }
+
+    #[test]
+    fn corrupted_caches_are_ignored() {
+        let dir = TempDir::new("corrupted");
+        let (files, cache) = cached(&dir);
+        let bytes = fs::read(&cache).unwrap();
+        // Every flipped byte is either detected or leaves a trie that can be searched without panicking
+        let mut detected = 0;
+        for pos in 0..bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[pos] ^= 0xFF;
+            fs::write(&cache, &corrupted).unwrap();
+            match load(&cache, &files).unwrap().1 {
+                Some(datasets) => {
+                    let trie = &datasets.trie;
+                    let _ = trie.find_value("rightarrow");
+                    let _ = trie.find_infix_matches(b"arrow");
+                    let _ = trie.find_description_matches("double");
+                    assert!(trie.iter(0).count() > 0);
+                }
+                None => detected += 1,
+            }
+        }
+        assert!(detected > bytes.len() / 2);
+    }
+
+    #[test]
+    fn truncated_caches_are_ignored() {
+        let dir = TempDir::new("truncated");
+        let (files, cache) = cached(&dir);
+        let bytes = fs::read(&cache).unwrap();
+        for len in [0, 3, bytes.len() / 2, bytes.len() - 1] {
+            fs::write(&cache, &bytes[..len]).unwrap();
+            let (sources, datasets) = load(&cache, &files).unwrap();
+            assert!(datasets.is_none());
+            assert_eq!(sources.len(), 1);
+        }
+        // A missing cache is not an error either
+        fs::remove_file(&cache).unwrap();
+        assert!(load(&cache, &files).unwrap().1.is_none());
+    }
+}
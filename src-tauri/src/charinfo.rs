@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_script::UnicodeScript;
+
+/// Properties of a single character, looked up from the bundled Unicode Character Database tables
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CharInfo {
+    /// ex. "U+03B1"
+    pub codepoint: String,
+    /// Official Unicode name, None if the character has none (ex. private use)
+    pub name: Option<String>,
+    /// Two-letter general category (ex. "Ll")
+    pub category: String,
+    /// ex. "Lowercase Letter"
+    pub category_name: String,
+    /// None if the character is outside of any block
+    pub block: Option<String>,
+    /// ex. "Greek", "Common"
+    pub script: String,
+}
+
+impl CharInfo {
+    pub fn new(ch: char) -> Self {
+        let (category, category_name) = category_names(get_general_category(ch));
+        CharInfo {
+            codepoint: format!("U+{:04X}", ch as u32),
+            name: unicode_names2::name(ch).map(|n| n.to_string()),
+            category: category.to_string(),
+            category_name: category_name.to_string(),
+            block: unicode_blocks::find_unicode_block(ch).map(|b| b.name().to_string()),
+            script: ch.script().full_name().to_string(),
+        }
+    }
+}
+
+// Returns the abbreviation and the long name of a general category
+fn category_names(category: GeneralCategory) -> (&'static str, &'static str) {
+    use GeneralCategory::*;
+    match category {
+        UppercaseLetter => ("Lu", "Uppercase Letter"),
+        LowercaseLetter => ("Ll", "Lowercase Letter"),
+        TitlecaseLetter => ("Lt", "Titlecase Letter"),
+        ModifierLetter => ("Lm", "Modifier Letter"),
+        OtherLetter => ("Lo", "Other Letter"),
+        NonspacingMark => ("Mn", "Nonspacing Mark"),
+        SpacingMark => ("Mc", "Spacing Mark"),
+        EnclosingMark => ("Me", "Enclosing Mark"),
+        DecimalNumber => ("Nd", "Decimal Number"),
+        LetterNumber => ("Nl", "Letter Number"),
+        OtherNumber => ("No", "Other Number"),
+        ConnectorPunctuation => ("Pc", "Connector Punctuation"),
+        DashPunctuation => ("Pd", "Dash Punctuation"),
+        OpenPunctuation => ("Ps", "Open Punctuation"),
+        ClosePunctuation => ("Pe", "Close Punctuation"),
+        InitialPunctuation => ("Pi", "Initial Punctuation"),
+        FinalPunctuation => ("Pf", "Final Punctuation"),
+        OtherPunctuation => ("Po", "Other Punctuation"),
+        MathSymbol => ("Sm", "Math Symbol"),
+        CurrencySymbol => ("Sc", "Currency Symbol"),
+        ModifierSymbol => ("Sk", "Modifier Symbol"),
+        OtherSymbol => ("So", "Other Symbol"),
+        SpaceSeparator => ("Zs", "Space Separator"),
+        LineSeparator => ("Zl", "Line Separator"),
+        ParagraphSeparator => ("Zp", "Paragraph Separator"),
+        Control => ("Cc", "Control"),
+        Format => ("Cf", "Format"),
+        Surrogate => ("Cs", "Surrogate"),
+        PrivateUse => ("Co", "Private Use"),
+        // GeneralCategory is non-exhaustive
+        _ => ("Cn", "Unassigned"),
+    }
+}
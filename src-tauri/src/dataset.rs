@@ -0,0 +1,850 @@
+use crate::trie::{Candidate, Trie};
+use std::fs::{self, File};
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+/// Separates a dataset namespace from an alias (ex. `math:alpha`)
+pub const NAMESPACE_SEPARATOR: char = ':';
+/// Directive line declaring the namespace of a dataset (ex. `#!namespace math`)
+pub const NAMESPACE_DIRECTIVE: &str = "#!namespace";
+/// Name of the dataset of user-defined aliases (`user.csv`), which is edited from the app
+pub const USER_DATASET: &str = "user";
+
+/// A parsed line of a dataset file
+#[derive(Debug, PartialEq)]
+pub enum DatasetLine {
+    Comment,
+    Namespace(String),
+    Entry {
+        alias: String,
+        value: char,
+        description: Option<String>,
+    },
+}
+
+/// Parses a line of a dataset file (a csv file of two colums. It contains comments starting with '#')
+///
+/// Columns may be quoted as in CSV (`"a,b"`, with `""` for a quote), but not span lines. A UTF-8 BOM and a trailing CR are ignored
+pub fn parse_line(line: &str) -> anyhow::Result<DatasetLine> {
+    let line = clean_line(line);
+    if let Some(rest) = line.strip_prefix(NAMESPACE_DIRECTIVE) {
+        // Namespace directive. The name follows after whitespace, and is checked like an alias
+        let name = parse_alias(rest)
+            .ok()
+            .filter(|name| {
+                rest.starts_with(|c: char| c.is_whitespace())
+                    && !name.contains(|c: char| c.is_whitespace())
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Invalid namespace directive. Expected `{} <name>`, the name being a non-empty ASCII string without whitespace or '{}': {}",
+                    NAMESPACE_DIRECTIVE,
+                    NAMESPACE_SEPARATOR,
+                    line
+                )
+            })?;
+        return Ok(DatasetLine::Namespace(name));
+    }
+    if line.starts_with('#') {
+        return Ok(DatasetLine::Comment);
+    }
+    // Split the line into alias and character
+    split_entry(line)
+        .and_then(|columns| {
+            let alias = parse_alias(&columns.alias)?;
+            let (value, _) = if columns.value_quoted {
+                parse_value(&columns.value)?
+            } else {
+                parse_value(columns.value.trim())?
+            };
+            Ok(DatasetLine::Entry {
+                alias,
+                value,
+                description: columns.description,
+            })
+        })
+        .map_err(|e| anyhow::anyhow!("Invalid line format. {}: {}", e, line))
+}
+
+// Strips a UTF-8 BOM (at the start of a file) and the CR of a CRLF line ending
+fn clean_line(line: &str) -> &str {
+    let line = line.strip_prefix('\u{FEFF}').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// The columns of an entry line, unquoted but otherwise as written
+#[derive(Debug, PartialEq)]
+pub struct EntryColumns {
+    pub alias: String,
+    pub value: String,
+    /// Quoted values are not trimmed
+    pub value_quoted: bool,
+    /// The optional third column, trimmed unless quoted
+    pub description: Option<String>,
+}
+
+/// Splits an entry line into its alias and value columns
+///
+/// An unquoted value ends at the first comma after its character, so `comma,,` maps `comma` to `,`.
+/// The description follows that comma. Text after a closing quote and columns after the description are errors
+pub fn split_entry(line: &str) -> anyhow::Result<EntryColumns> {
+    let line = clean_line(line);
+    let (alias, rest) = match line.trim_start().strip_prefix('"') {
+        Some(quoted) => {
+            let (alias, rest) = read_quoted(quoted)?;
+            let rest = rest.trim_start().strip_prefix(',').ok_or_else(|| {
+                if rest.trim().is_empty() {
+                    anyhow::anyhow!("Comma separation not found after the alias")
+                } else {
+                    anyhow::anyhow!("Unexpected text after the quoted alias")
+                }
+            })?;
+            (alias, rest)
+        }
+        None => {
+            let (alias, rest) = line
+                .split_once(',')
+                .ok_or_else(|| anyhow::anyhow!("Comma separation not found"))?;
+            (alias.to_string(), rest)
+        }
+    };
+    match rest.trim().strip_prefix('"') {
+        // A lone quote is the character itself (as before quoting was supported)
+        Some(quoted) if !quoted.is_empty() => {
+            let (value, after) = read_quoted(quoted)?;
+            let after = after.trim();
+            let description = match after.strip_prefix(',') {
+                Some(description) => parse_description(description)?,
+                None if after.is_empty() => None,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Unexpected text after the quoted character"
+                    ))
+                }
+            };
+            Ok(EntryColumns {
+                alias,
+                value,
+                value_quoted: true,
+                description,
+            })
+        }
+        _ => {
+            // The value ends at the first comma after its character (or escape)
+            let (_, after) = parse_value(rest.trim_start())?;
+            let (value, description) = match after.find(',') {
+                Some(pos) => {
+                    let end = rest.len() - after.len() + pos;
+                    (&rest[..end], parse_description(&rest[end + 1..])?)
+                }
+                None => (rest, None),
+            };
+            Ok(EntryColumns {
+                alias,
+                value: value.to_string(),
+                value_quoted: false,
+                description,
+            })
+        }
+    }
+}
+
+// Reads the description column, which may be quoted. An empty description is None
+// It is the last column, so a description containing a comma must be quoted
+fn parse_description(column: &str) -> anyhow::Result<Option<String>> {
+    let column = column.trim();
+    let extra_column = || {
+        anyhow::anyhow!(
+            "Unexpected column after the description. Quote a description containing commas"
+        )
+    };
+    let description = match column.strip_prefix('"') {
+        Some(quoted) => {
+            let (description, after) = read_quoted(quoted)?;
+            let after = after.trim();
+            if after.starts_with(',') {
+                return Err(extra_column());
+            }
+            if !after.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Unexpected text after the quoted description"
+                ));
+            }
+            description
+        }
+        None if column.contains(',') => return Err(extra_column()),
+        None => column.to_string(),
+    };
+    Ok(Some(description).filter(|d| !d.is_empty()))
+}
+
+// Reads a quoted column, starting after its opening quote. Returns the text and the rest of the line after the closing quote
+fn read_quoted(s: &str) -> anyhow::Result<(String, &str)> {
+    let mut text = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '"' {
+            text.push(c);
+        } else if s[i + 1..].starts_with('"') {
+            text.push('"');
+            chars.next();
+        } else {
+            return Ok((text, &s[i + 1..]));
+        }
+    }
+    Err(anyhow::anyhow!(
+        "Closing quote not found. Quoted columns cannot span lines"
+    ))
+}
+
+/// Checks the columns of an entry and returns the alias and the character
+///
+/// The alias must be a non-empty ASCII string without the namespace separator.
+/// The value must be a single character or escape. Surrounding whitespace is ignored
+pub fn parse_entry(alias: &str, value: &str) -> anyhow::Result<(String, char)> {
+    let alias = parse_alias(alias)?;
+    let (value, rest) = parse_value(value.trim())?;
+    if !rest.is_empty() {
+        return Err(anyhow::anyhow!(
+            "More than one character given: {}{}",
+            value,
+            rest
+        ));
+    }
+    Ok((alias, value))
+}
+
+fn parse_alias(alias: &str) -> anyhow::Result<String> {
+    let alias = alias.trim();
+    if alias.is_empty() {
+        return Err(anyhow::anyhow!("Alias string is empty"));
+    }
+    if !alias.is_ascii() {
+        return Err(anyhow::anyhow!("Alias string is not ASCII"));
+    }
+    if alias.contains(NAMESPACE_SEPARATOR) {
+        return Err(anyhow::anyhow!(
+            "Alias string contains '{}'",
+            NAMESPACE_SEPARATOR
+        ));
+    }
+    Ok(alias.to_string())
+}
+
+/// Parses the value column: a `\u{...}` or `U+XXXX` escape, or else its first character
+///
+/// Returns the character and the text after it, which is ignored
+pub fn parse_value(value: &str) -> anyhow::Result<(char, &str)> {
+    if let Some(rest) = value.strip_prefix("\\u{") {
+        let end = rest
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("Escape is not closed: {}", value))?;
+        return Ok((parse_codepoint(&rest[..end])?, &rest[end + 1..]));
+    }
+    if let Some(rest) = value.strip_prefix("U+") {
+        let len = rest
+            .bytes()
+            .take(6)
+            .take_while(|b| b.is_ascii_hexdigit())
+            .count();
+        // Otherwise it is just the character 'U'
+        if len >= 4 {
+            return Ok((parse_codepoint(&rest[..len])?, &rest[len..]));
+        }
+    }
+    let ch = value
+        .chars()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No character found after comma"))?;
+    Ok((ch, &value[ch.len_utf8()..]))
+}
+
+fn parse_codepoint(hex: &str) -> anyhow::Result<char> {
+    if hex.is_empty() || hex.len() > 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(anyhow::anyhow!("Invalid codepoint: {}", hex));
+    }
+    u32::from_str_radix(hex, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| anyhow::anyhow!("Invalid codepoint: {}", hex))
+}
+
+/// Formats an entry as a dataset line, so that `parse_line` reads it back
+///
+/// Invisible characters are written as `U+XXXX` escapes
+pub fn format_entry(alias: &str, value: char) -> String {
+    let alias = if alias.starts_with('#') || alias.contains([',', '"']) {
+        format!("\"{}\"", alias.replace('"', "\"\""))
+    } else {
+        alias.to_string()
+    };
+    let invisible = value.is_whitespace()
+        || value.is_control()
+        || get_general_category(value) == GeneralCategory::Format;
+    match value {
+        _ if invisible => format!("{},U+{:04X}", alias, value as u32),
+        '"' => format!("{},\"\"\"\"", alias),
+        ',' => format!("{},\",\"", alias),
+        _ => format!("{},{}", alias, value),
+    }
+}
+
+/// Summary of a parsed dataset file
+#[derive(Debug, Default)]
+pub struct ParseReport {
+    /// Number of entries in the file
+    pub entries: usize,
+    /// Entries that could not be appended to the trie (ex. the same candidate already exists)
+    pub warnings: Vec<String>,
+}
+
+// //1) parse the unicode config file (a csv file of two colums. It contains comments starting with '#')
+// //2) appends all the parsed data into the trie
+// //3) if the file declares a namespace ("#!namespace <name>" before any entry), every alias is also appended as "<name>:<alias>"
+pub fn parse_unicode_dataset(path: &Path, trie: &mut Trie) -> anyhow::Result<ParseReport> {
+    let file = File::open(path).map_err(|e| anyhow::anyhow!("Failed to open file: {}", e))?;
+    parse_dataset(io::BufReader::new(file), &dataset_name(path), trie)
+}
+
+/// Parses a dataset from a reader into the trie as the dataset `name`
+///
+/// Same as `parse_unicode_dataset`, for data that does not come from a file
+pub fn parse_dataset(
+    reader: impl BufRead,
+    name: &str,
+    trie: &mut Trie,
+) -> anyhow::Result<ParseReport> {
+    let mut namespace: Option<String> = None;
+    let mut report = ParseReport::default();
+    let dataset = trie.add_dataset(name);
+
+    for line in reader.lines() {
+        let line = line.map_err(|e| anyhow::anyhow!("Failed to read line: {}", e))?;
+        match parse_line(&line)? {
+            DatasetLine::Comment => continue,
+            DatasetLine::Namespace(name) => {
+                if report.entries > 0 || namespace.is_some() {
+                    return Err(anyhow::anyhow!(
+                        "Namespace must be declared once, before any entry: {}",
+                        line
+                    ));
+                }
+                namespace = Some(name);
+            }
+            DatasetLine::Entry {
+                alias,
+                value,
+                description,
+            } => {
+                report.entries += 1;
+                let namespace = namespace.as_deref();
+                let description = description.as_deref();
+                for e in append_entry(trie, namespace, alias, value, description, dataset) {
+                    report.warnings.push(e.to_string());
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Parses dataset files into a new trie, in the given order, and builds its infix index
+///
+/// `on_file` is called after each file with its position in `files` and its report
+pub fn load_datasets(
+    files: &[PathBuf],
+    mut on_file: impl FnMut(usize, &Path, ParseReport),
+) -> anyhow::Result<Trie> {
+    let mut trie = Trie::new();
+    for (i, path) in files.iter().enumerate() {
+        // Parse the unicode config file and append data to the trie
+        let report = parse_unicode_dataset(path, &mut trie)
+            .map_err(|e| anyhow::anyhow!("Failed to parse dataset file {:?}: {}", path, e))?;
+        on_file(i, path, report);
+    }
+    trie.build_infix_index();
+    Ok(trie)
+}
+
+/// Appends an entry to the trie, along with its qualified alias if the dataset has a namespace
+///
+/// Both aliases share the description, if any
+///
+/// Returns the errors of `Trie::append_leaf` (ex. the same candidate already exists)
+pub fn append_entry(
+    trie: &mut Trie,
+    namespace: Option<&str>,
+    alias: String,
+    value: char,
+    description: Option<&str>,
+    dataset: usize,
+) -> Vec<anyhow::Error> {
+    add_entry(
+        trie,
+        namespace,
+        alias,
+        value,
+        description,
+        dataset,
+        Trie::append_candidate,
+    )
+}
+
+/// Like `append_entry`, but keeps the infix and word indices of the trie up to date (see `Trie::insert_candidate`)
+pub fn insert_entry(
+    trie: &mut Trie,
+    namespace: Option<&str>,
+    alias: String,
+    value: char,
+    description: Option<&str>,
+    dataset: usize,
+) -> Vec<anyhow::Error> {
+    add_entry(
+        trie,
+        namespace,
+        alias,
+        value,
+        description,
+        dataset,
+        Trie::insert_candidate,
+    )
+}
+
+fn add_entry(
+    trie: &mut Trie,
+    namespace: Option<&str>,
+    alias: String,
+    value: char,
+    description: Option<&str>,
+    dataset: usize,
+    add: fn(&mut Trie, String, Candidate) -> anyhow::Result<()>,
+) -> Vec<anyhow::Error> {
+    let candidate = Candidate {
+        value,
+        dataset,
+        description: description.map(|d| trie.add_description(d)),
+    };
+    let mut errors = Vec::new();
+    if let Some(ns) = namespace {
+        let qualified = format!("{}{}{}", ns, NAMESPACE_SEPARATOR, alias);
+        if let Err(e) = add(trie, qualified, candidate) {
+            errors.push(e);
+        }
+    }
+    if let Err(e) = add(trie, alias, candidate) {
+        errors.push(e);
+    }
+    errors
+}
+
+/// Name of the dataset stored at path (the file name without extension)
+pub fn dataset_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The dataset of user-defined aliases
+///
+/// Lines are kept as they are (including comments), so that editing an entry leaves the rest of the file untouched
+pub struct UserDataset {
+    path: PathBuf,
+    lines: Vec<String>,
+}
+
+impl UserDataset {
+    /// Reads the user dataset. If the file does not exist, an empty dataset is created (but not saved)
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let lines = match fs::read_to_string(path) {
+            Ok(text) => text.lines().map(|l| l.to_string()).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                vec!["# User-defined aliases. Managed by UniAlias".to_string()]
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(UserDataset {
+            path: path.to_path_buf(),
+            lines,
+        })
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        fs::write(&self.path, text)?;
+        Ok(())
+    }
+
+    pub fn namespace(&self) -> Option<String> {
+        self.lines.iter().find_map(|l| match parse_line(l) {
+            Ok(DatasetLine::Namespace(name)) => Some(name),
+            _ => None,
+        })
+    }
+
+    /// Returns all valid entries, in file order
+    pub fn entries(&self) -> Vec<(String, char)> {
+        self.lines
+            .iter()
+            .filter_map(|l| match parse_line(l) {
+                Ok(DatasetLine::Entry { alias, value, .. }) => Some((alias, value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Appends an entry. Returns Err if the same entry already exists
+    pub fn add(&mut self, alias: &str, value: char) -> anyhow::Result<()> {
+        if self.position(alias, value).is_some() {
+            return Err(anyhow::anyhow!(
+                "Alias {} is already mapped to {}",
+                alias,
+                value
+            ));
+        }
+        self.lines.push(format_entry(alias, value));
+        Ok(())
+    }
+
+    /// Removes an entry. Returns Err if it does not exist
+    pub fn remove(&mut self, alias: &str, value: char) -> anyhow::Result<()> {
+        let pos = self.existing_position(alias, value)?;
+        self.lines.remove(pos);
+        Ok(())
+    }
+
+    /// Replaces an entry in place, keeping its description
+    /// Returns the description, or Err if the entry does not exist or the new entry already does
+    pub fn replace(
+        &mut self,
+        old_alias: &str,
+        old_value: char,
+        alias: &str,
+        value: char,
+    ) -> anyhow::Result<Option<String>> {
+        let pos = self.existing_position(old_alias, old_value)?;
+        if self.position(alias, value).is_some_and(|p| p != pos) {
+            return Err(anyhow::anyhow!(
+                "Alias {} is already mapped to {}",
+                alias,
+                value
+            ));
+        }
+        let mut line = format_entry(alias, value);
+        let description = match parse_line(&self.lines[pos]) {
+            Ok(DatasetLine::Entry { description, .. }) => description,
+            _ => None,
+        };
+        if let Some(d) = &description {
+            line = format!("{},\"{}\"", line, d.replace('"', "\"\""));
+        }
+        self.lines[pos] = line;
+        Ok(description)
+    }
+
+    fn existing_position(&self, alias: &str, value: char) -> anyhow::Result<usize> {
+        self.position(alias, value)
+            .ok_or_else(|| anyhow::anyhow!("Alias {} is not mapped to {}", alias, value))
+    }
+
+    fn position(&self, alias: &str, value: char) -> Option<usize> {
+        self.lines.iter().position(|l| match parse_line(l) {
+            Ok(DatasetLine::Entry {
+                alias: a, value: v, ..
+            }) => a == alias && v == value,
+            _ => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: &str) -> (String, char) {
+        match parse_line(line).unwrap() {
+            DatasetLine::Entry { alias, value, .. } => (alias, value),
+            other => panic!("{:?} is not an entry", other),
+        }
+    }
+
+    #[test]
+    fn namespace_directive() {
+        let namespace = |line: &str| match parse_line(line) {
+            Ok(DatasetLine::Namespace(name)) => Some(name),
+            _ => None,
+        };
+        assert_eq!(namespace("#!namespace math"), Some("math".to_string()));
+        assert_eq!(namespace("#!namespace\tmath  \r"), Some("math".to_string()));
+        assert!(parse_line("#!namespacefoo bar").is_err());
+        assert!(parse_line("#!namespace").is_err());
+        assert!(parse_line("#!namespace foo bar").is_err());
+        assert!(parse_line("#!namespace a:b").is_err());
+        assert!(parse_line("#!namespace μ").is_err());
+    }
+
+    #[test]
+    fn namespaced_dataset() {
+        let text = "# Greek\n#!namespace math\nalpha,α\n";
+        let mut trie = Trie::new();
+        let report = parse_dataset(text.as_bytes(), "greek", &mut trie).unwrap();
+        assert_eq!((report.entries, report.warnings.len()), (1, 0));
+        // Reachable by the plain and the qualified alias
+        assert_eq!(trie.find_value("alpha").unwrap()[0].value, 'α');
+        assert_eq!(trie.find_value("math:alpha").unwrap()[0].value, 'α');
+
+        let late = "alpha,α\n#!namespace math\n";
+        assert!(parse_dataset(late.as_bytes(), "greek", &mut Trie::new()).is_err());
+        let twice = "#!namespace math\n#!namespace greek\n";
+        assert!(parse_dataset(twice.as_bytes(), "greek", &mut Trie::new()).is_err());
+    }
+
+    #[test]
+    fn several_values_per_alias() {
+        let text = "lwhtlenticular,〖\nlwhtlenticular,〘\nlwhtlenticular,〖\n";
+        let mut trie = Trie::new();
+        let report = parse_dataset(text.as_bytes(), "cjk", &mut trie).unwrap();
+        // Both values are kept, the repeated one is reported
+        assert_eq!((report.entries, report.warnings.len()), (3, 1));
+        let values: Vec<char> = trie
+            .find_value("lwhtlenticular")
+            .unwrap()
+            .iter()
+            .map(|c| c.value)
+            .collect();
+        assert_eq!(values, ['〖', '〘']);
+    }
+
+    #[test]
+    fn plain_lines() {
+        assert_eq!(entry("alpha,α"), ("alpha".to_string(), 'α'));
+        assert_eq!(entry(" alpha , α "), ("alpha".to_string(), 'α'));
+        assert_eq!(entry("comma,,"), ("comma".to_string(), ','));
+        assert_eq!(entry("quote,\""), ("quote".to_string(), '"'));
+        assert_eq!(entry("\u{FEFF}alpha,α\r"), ("alpha".to_string(), 'α'));
+        assert_eq!(
+            parse_line("\u{FEFF}# comment").unwrap(),
+            DatasetLine::Comment
+        );
+    }
+
+    #[test]
+    fn quoted_columns() {
+        assert_eq!(entry("space,\" \""), ("space".to_string(), ' '));
+        assert_eq!(entry("comma,\",\"  "), ("comma".to_string(), ','));
+        assert_eq!(entry("quote,\"\"\"\""), ("quote".to_string(), '"'));
+        assert_eq!(entry("\"#hash\",#"), ("#hash".to_string(), '#'));
+        assert_eq!(entry("\"a,b\" ,c"), ("a,b".to_string(), 'c'));
+        assert!(parse_line("space,\" x").is_err());
+        assert!(parse_line("space,\" \" x").is_err());
+        assert!(parse_line("\"alpha\" α").is_err());
+        // Text after a closing quote is an error in every column
+        let error = |line: &str| parse_line(line).unwrap_err().to_string();
+        assert!(error("\"a\"x,b").contains("after the quoted alias"));
+        assert!(error("a,\"b\"x").contains("after the quoted character"));
+        assert!(error("a,b,\"c\"x").contains("after the quoted description"));
+    }
+
+    #[test]
+    fn entries_from_columns() {
+        let parsed = |alias: &str, value: &str| parse_entry(alias, value).ok();
+        assert_eq!(parsed(" alpha ", " α "), Some(("alpha".to_string(), 'α')));
+        assert_eq!(
+            parsed("zwsp", "U+200B"),
+            Some(("zwsp".to_string(), '\u{200B}'))
+        );
+        assert_eq!(parsed("comma", ","), Some(("comma".to_string(), ',')));
+        // Rejected like the TrailingJunk lint, instead of keeping the first character
+        assert_eq!(parsed("alpha", "αβ"), None);
+        assert_eq!(parsed("zwsp", "\\u{200B}x"), None);
+        assert_eq!(parsed("", "α"), None);
+        assert_eq!(parsed("alpha", ""), None);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(entry("zwsp,\\u{200B}"), ("zwsp".to_string(), '\u{200B}'));
+        assert_eq!(entry("nbsp,U+00A0"), ("nbsp".to_string(), '\u{A0}'));
+        assert_eq!(entry("grin,\"U+1F600\""), ("grin".to_string(), '😀'));
+        // Not an escape
+        assert_eq!(entry("u,U+"), ("u".to_string(), 'U'));
+        assert_eq!(entry("backslash,\\"), ("backslash".to_string(), '\\'));
+        assert!(parse_line("surrogate,U+D800").is_err());
+        assert!(parse_line("open,\\u{41").is_err());
+        assert!(parse_line("empty,\\u{}").is_err());
+    }
+
+    #[test]
+    fn descriptions() {
+        let described = |line: &str| match parse_line(line).unwrap() {
+            DatasetLine::Entry {
+                value, description, ..
+            } => (value, description),
+            other => panic!("{:?} is not an entry", other),
+        };
+        let text = |s: &str| Some(s.to_string());
+        assert_eq!(described("lcorner,「"), ('「', None));
+        assert_eq!(
+            described("lcorner,「,left corner bracket"),
+            ('「', text("left corner bracket"))
+        );
+        assert_eq!(
+            described("lcorner, 「 , left corner "),
+            ('「', text("left corner"))
+        );
+        assert_eq!(described("comma,,,comma"), (',', text("comma")));
+        assert_eq!(
+            described("comma,\",\",\"comma, the\""),
+            (',', text("comma, the"))
+        );
+        assert_eq!(
+            described("zwsp,U+200B,zero width space"),
+            ('\u{200B}', text("zero width space"))
+        );
+        assert_eq!(described("empty,x,"), ('x', None));
+        assert!(parse_line("quoted,x,\"unclosed").is_err());
+        // The description is the last column
+        for line in ["extra,x,one,two", "extra,x,\"one\",two", "extra,x,one,"] {
+            let error = parse_line(line).unwrap_err().to_string();
+            assert!(error.contains("column after the description"), "{}", line);
+        }
+    }
+
+    #[test]
+    fn crlf_and_bom() {
+        // The CR of a line ending is not part of a quoted column
+        let text = "alpha,\"α\",\"first letter\"\r\nbeta,β\r\n";
+        let mut trie = Trie::new();
+        let report = parse_dataset(text.as_bytes(), "greek", &mut trie).unwrap();
+        assert_eq!((report.entries, report.warnings.len()), (2, 0));
+        let alpha = trie.find_value("alpha").unwrap()[0];
+        assert_eq!(trie.description(&alpha), Some("first letter"));
+
+        // Quoted columns cannot span lines, whatever the line ending
+        for text in ["alpha,α,\"first\r\nletter\"\r\n", "alpha,\"α\r\n\"\r\n"] {
+            let error = parse_dataset(text.as_bytes(), "greek", &mut Trie::new()).unwrap_err();
+            assert!(
+                error.to_string().contains("Closing quote not found"),
+                "{:?}",
+                text
+            );
+        }
+
+        // A BOM does not hide a directive on the first line
+        let text = "\u{FEFF}#!namespace math\r\nalpha,α\r\n";
+        let mut trie = Trie::new();
+        parse_dataset(text.as_bytes(), "greek", &mut trie).unwrap();
+        assert_eq!(trie.find_value("math:alpha").unwrap()[0].value, 'α');
+    }
+
+    #[test]
+    fn formatted_entries_parse_back() {
+        for (alias, value) in [
+            ("alpha", 'α'),
+            ("space", ' '),
+            ("nbsp", '\u{A0}'),
+            ("zwsp", '\u{200B}'),
+            ("tab", '\t'),
+            ("comma", ','),
+            ("quote", '"'),
+            ("u", 'U'),
+            ("backslash", '\\'),
+            ("#hash", '#'),
+            ("a,\"b\"", 'c'),
+        ] {
+            let line = format_entry(alias, value);
+            assert_eq!(entry(&line), (alias.to_string(), value), "{}", line);
+        }
+    }
+
+    // A user dataset file under the temporary directory, removed when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, text: Option<&str>) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("unialias-{}-{}.csv", name, std::process::id()));
+            match text {
+                Some(text) => fs::write(&path, text).unwrap(),
+                None => {
+                    let _ = fs::remove_file(&path);
+                }
+            }
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn user_dataset_round_trip() {
+        let file = TempFile::new("round-trip", None);
+        // A missing file starts with a header comment
+        let mut user = UserDataset::open(&file.0).unwrap();
+        assert!(user.entries().is_empty());
+        assert_eq!(user.namespace(), None);
+        user.add("alpha", 'α').unwrap();
+        user.add("comma", ',').unwrap();
+        user.add("alpha", 'a').unwrap();
+        user.remove("alpha", 'α').unwrap();
+        user.save().unwrap();
+
+        let mut user = UserDataset::open(&file.0).unwrap();
+        assert_eq!(
+            user.entries(),
+            [("comma".to_string(), ','), ("alpha".to_string(), 'a')]
+        );
+        // Edited in place
+        user.replace("comma", ',', "semicolon", ';').unwrap();
+        user.save().unwrap();
+        let text = fs::read_to_string(&file.0).unwrap();
+        assert_eq!(
+            text,
+            "# User-defined aliases. Managed by UniAlias\nsemicolon,;\nalpha,a\n"
+        );
+    }
+
+    #[test]
+    fn user_dataset_errors() {
+        let file = TempFile::new("errors", Some("alpha,α\nbeta,β\n"));
+        let mut user = UserDataset::open(&file.0).unwrap();
+        assert!(user.add("alpha", 'α').is_err());
+        assert!(user.remove("alpha", 'a').is_err());
+        assert!(user.remove("gamma", 'γ').is_err());
+        assert!(user.replace("gamma", 'γ', "delta", 'δ').is_err());
+        assert!(user.replace("alpha", 'α', "beta", 'β').is_err());
+        // Replacing an entry by itself is not a duplicate
+        user.replace("alpha", 'α', "alpha", 'α').unwrap();
+        assert_eq!(user.entries().len(), 2);
+        // A directory cannot be read as a dataset
+        assert!(UserDataset::open(&std::env::temp_dir()).is_err());
+    }
+
+    #[test]
+    fn user_dataset_keeps_other_lines() {
+        let text = "# My aliases\n#!namespace my\n\n\"a,b\",x,two letters\n# trailing\nbad line\n";
+        let file = TempFile::new("other-lines", Some(text));
+        let mut user = UserDataset::open(&file.0).unwrap();
+        assert_eq!(user.namespace(), Some("my".to_string()));
+        assert_eq!(user.entries(), [("a,b".to_string(), 'x')]);
+        user.add("c", 'y').unwrap();
+        let description = user.replace("a,b", 'x', "ab", 'z').unwrap();
+        assert_eq!(description.as_deref(), Some("two letters"));
+        user.remove("c", 'y').unwrap();
+        user.save().unwrap();
+        // Comments, the directive, blank and invalid lines stay, and the description is kept
+        let text = fs::read_to_string(&file.0).unwrap();
+        assert_eq!(
+            text,
+            "# My aliases\n#!namespace my\n\nab,z,\"two letters\"\n# trailing\nbad line\n"
+        );
+        let user = UserDataset::open(&file.0).unwrap();
+        assert_eq!(user.namespace(), Some("my".to_string()));
+        assert_eq!(user.entries(), [("ab".to_string(), 'z')]);
+    }
+}
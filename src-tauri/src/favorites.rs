@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+/// A pinned entry. Either a candidate of an alias or a raw character
+///
+/// A candidate is pinned by its value rather than its position among the candidates of the alias,
+/// which changes when datasets are enabled, disabled or edited
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Favorite {
+    Alias { alias: String, value: char },
+    Char { value: char },
+}
+
+/// Ordered list of favorites, persisted as a JSON file
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Favorites {
+    pub items: Vec<Favorite>,
+}
+
+impl Favorites {
+    /// Reads the favorites from a JSON file. A missing file is treated as an empty list
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(io::BufReader::new(file))?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Favorites::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Appends a favorite. Returns false if it already exists
+    pub fn add(&mut self, favorite: Favorite) -> bool {
+        if self.items.contains(&favorite) {
+            return false;
+        }
+        self.items.push(favorite);
+        true
+    }
+
+    /// Removes a favorite. Returns false if it did not exist
+    pub fn remove(&mut self, favorite: &Favorite) -> bool {
+        let len = self.items.len();
+        self.items.retain(|f| f != favorite);
+        self.items.len() != len
+    }
+
+    /// Returns the position of an alias candidate in the list
+    pub fn position_of_alias(&self, alias: &str, value: char) -> Option<usize> {
+        self.items.iter().position(|f| match f {
+            Favorite::Alias { alias: a, value: v } => a == alias && *v == value,
+            Favorite::Char { .. } => false,
+        })
+    }
+}
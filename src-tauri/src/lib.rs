@@ -1,78 +1,143 @@
-use enigo::{Enigo, Keyboard, Settings};
+use charinfo::CharInfo;
+use dataset::{dataset_name, UserDataset, NAMESPACE_SEPARATOR, USER_DATASET};
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+use favorites::{Favorite, Favorites};
+use matching::{
+    favorite_matches, find_candidate, find_pinned_candidate, resolve_favorite, MatchCache,
+    MatchData, MatchKind, Ranking,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::{self, File};
+use settings::{AppSettings, HotkeyAction, InsertMethod, MatchMode};
+use std::fs;
 use std::path::PathBuf;
-use std::io::{self, BufRead};
-use std::path::Path;
-use std::sync::RwLock;
-use tauri::menu::{Menu, MenuItem};
+use std::sync::{Mutex, RwLock};
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
-use tauri::{App, Emitter, Manager};
-use trie::{Trie, TrieNodeContent};
+use tauri::{App, AppHandle, Emitter, Manager, Wry};
+use trie::Trie;
+use usage::Usage;
 
+pub mod autostart;
+pub mod cache;
+pub mod charinfo;
+pub mod dataset;
+pub mod favorites;
+pub mod lint;
+pub mod matching;
+pub mod settings;
 pub mod trie;
+pub mod usage;
 
+const TRAY_ID: &str = "main";
+/// Menu item ids of favorites are this prefix followed by the position in the list
+const FAVORITE_MENU_PREFIX: &str = "favorite:";
+/// Menu item ids of recent characters are these prefixes followed by the position in the history
+const RECENT_INSERT_MENU_PREFIX: &str = "recent-insert:";
+const RECENT_COPY_MENU_PREFIX: &str = "recent-copy:";
+/// Menu item ids of dataset toggles are this prefix followed by the dataset name
+const DATASET_MENU_PREFIX: &str = "dataset:";
+/// Number of recently inserted characters that are remembered
+const RECENT_LIMIT: usize = 10;
+
+/// A page of matches, along with the number of all matches
 #[derive(Serialize, Deserialize, Debug)]
-struct MatchData {
-    matchstr: String,
-    matchlen: usize,
-    value: char,
+struct MatchPage {
+    total: usize,
+    matches: Vec<MatchData>,
+    kind: MatchKind, // whether the prefix matches are partial, corrections ("did you mean") or found by description
 }
 
-/// Finds (cnt)-top matches for a given input string
-/// if the input is empty or not ASCII it returns an empty list
+/// Finds (cnt) matches for a given input string, skipping the first (offset) matches (see `matching::find_matches`)
+/// if cnt is not given, the configured maximum number of results is used
+///
+/// If dataset is given, only its candidates are listed. If matching is given, it overrides the configured matching mode
+/// Matches are ordered as configured
+///
+/// If no alias starts with the whole input, candidates whose description matches it are listed instead (see `matching::find_description_matches`),
+/// or else spelling corrections if enabled (see `matching::find_corrections`)
+///
+/// Further pages of a query are sliced from the last search when possible (see `matching::MatchCache`)
 #[tauri::command]
-fn find_matches(input: String, cnt: usize, appstate: tauri::State<'_, AppState>) -> Vec<MatchData> {
+fn find_matches(
+    input: String,
+    cnt: Option<usize>,
+    offset: Option<usize>,
+    dataset: Option<String>,
+    matching: Option<MatchMode>,
+    appstate: tauri::State<'_, AppState>,
+) -> MatchPage {
+    let settings = appstate.settings.read().unwrap();
+    let cnt = cnt.unwrap_or(settings.max_results);
+    let offset = offset.unwrap_or(0);
     let trie = appstate.trie.read().unwrap();
-    let mut result = Vec::with_capacity(cnt);
-    let mut counter = cnt;
-    if cnt == 0 || input.len() == 0 || !input.is_ascii() {
-        return result;
-    }
-
-    let (midx, mlen) = trie.find_max_match(input.as_bytes());
-    if midx == 0 {
-        //root
-        return result;
-    }
-    for (idx, _) in trie.iter(&midx) {
-        let node = &(*trie).nodes[idx];
-        if let TrieNodeContent::Leaf { data } = node.content {
-            result.push(MatchData {
-                matchstr: node.value_str().to_owned(),
-                matchlen: mlen,
-                value: data,
-            });
-            counter -= 1;
-            if counter == 0 {
-                break;
+    let favorites = appstate.favorites.read().unwrap();
+    // An unknown dataset matches no candidate
+    let dataset = dataset.map(|name| {
+        trie.datasets
+            .iter()
+            .position(|d| *d == name)
+            .unwrap_or(usize::MAX)
+    });
+    let matching = matching.unwrap_or(settings.matching);
+    let usage = appstate.usage.read().unwrap();
+    let query = (input, dataset, matching);
+    let mut cache = appstate.match_cache.lock().unwrap();
+    if let Some(limit) = MatchCache::search_limit(cache.as_ref(), &query, offset, cnt) {
+        let ranking = Ranking::new(settings.order, &trie, &settings.dataset_priority, &usage);
+        let (input, dataset, matching) = (&query.0, query.1, query.2);
+        let mut result =
+            matching::find_matches(&trie, &favorites, input, dataset, matching, &ranking, limit);
+        if result.kind == MatchKind::Partial {
+            let described = matching::find_description_matches(
+                &trie, &favorites, input, dataset, &ranking, limit,
+            );
+            if described.kind == MatchKind::Description {
+                result = described;
+            }
+        }
+        if result.kind == MatchKind::Partial && settings.suggest_corrections {
+            let corrections = matching::find_corrections(
+                &trie, &favorites, input, dataset, matching, &ranking, limit,
+            );
+            if corrections.kind == MatchKind::Corrected {
+                result = corrections;
             }
         }
+        *cache = Some(MatchCache {
+            query,
+            limit,
+            result,
+        });
+    }
+    let result = &cache.as_ref().unwrap().result;
+    MatchPage {
+        total: result.total,
+        matches: result
+            .matches
+            .iter()
+            .skip(offset)
+            .take(cnt)
+            .cloned()
+            .collect(),
+        kind: result.kind,
     }
-    //log::info!("{:?}", result);
-    result
 }
 
 //gets the alias, searches it within the trie, and input the match through keyboard
 //window hiding is handled by frontend
 //not recives the value directly (more safe)
+//index selects among the candidates of the alias (defaults to the first one)
 #[tauri::command]
-fn select_alias(alias: String, appstate: tauri::State<'_, AppState>) -> bool {
-    let trie = appstate.trie.read().unwrap();
-    match trie.find_value(&alias) {
-        Ok(ch) => {
-            if let Ok(mut en) = Enigo::new(&Settings::default()) {
-                if let Ok(_) = en.text(&ch.to_string()) {
-                    true
-                } else {
-                    log::info!("Failed to input character: {}", ch);
-                    false
-                }
-            } else {
-                false
-            }
-        }
+fn select_alias(
+    alias: String,
+    index: Option<usize>,
+    app_handle: AppHandle,
+    appstate: tauri::State<'_, AppState>,
+) -> bool {
+    let index = index.unwrap_or(0);
+    let candidate = find_candidate(&appstate.trie.read().unwrap(), &alias, index);
+    match candidate {
+        Ok(candidate) => insert_and_record(&app_handle, candidate.value),
         Err(e) => {
             log::info!("Error finding alias: {}", e);
             false
@@ -80,113 +145,804 @@ fn select_alias(alias: String, appstate: tauri::State<'_, AppState>) -> bool {
     }
 }
 
-// Loads all datasets under the "dataset" directory in the app data directory
-// It expects each dataset to be in CSV format
-// Returns an error if the dataset cannot be loaded or parsed (error type is String)
+// Inputs the character through keyboard
+fn insert_char(ch: char, method: InsertMethod) -> bool {
+    if let Ok(mut en) = Enigo::new(&Settings::default()) {
+        let res = match method {
+            InsertMethod::Text => en.text(&ch.to_string()),
+            InsertMethod::Key => en.key(Key::Unicode(ch), Direction::Click),
+        };
+        if res.is_ok() {
+            true
+        } else {
+            log::info!("Failed to input character: {}", ch);
+            false
+        }
+    } else {
+        false
+    }
+}
+
+// Inputs the character with the configured method and adds it to the recent characters and the usage history
+// (used by the "repeat" hotkey action and the tray menu)
+fn insert_and_record(app_handle: &AppHandle, ch: char) -> bool {
+    let appstate = app_handle.state::<AppState>();
+    let method = appstate.settings.read().unwrap().insert_method;
+    let inserted = insert_char(ch, method);
+    if inserted {
+        let mut recent = appstate.recent.write().unwrap();
+        recent.retain(|c| *c != ch);
+        recent.insert(0, ch);
+        recent.truncate(RECENT_LIMIT);
+        drop(recent);
+        let mut usage = appstate.usage.write().unwrap();
+        usage.record(ch, usage::now());
+        appstate.invalidate_matches();
+        if let Err(e) = usage_path(app_handle).and_then(|path| usage.save(&path)) {
+            log::error!("Failed to save usage history: {}", e);
+        }
+        drop(usage);
+        update_tray_menu(app_handle);
+    }
+    inserted
+}
+
+/// Returns the favorites list
 #[tauri::command]
-fn load_dataset(
-    app_handle: tauri::AppHandle,
+fn list_favorites(appstate: tauri::State<'_, AppState>) -> Vec<Favorite> {
+    appstate.favorites.read().unwrap().items.clone()
+}
+
+/// Adds a favorite (alias candidate or raw character) and saves the list
+/// Returns false if it was already a favorite
+#[tauri::command]
+fn add_favorite(
+    favorite: Favorite,
+    app_handle: AppHandle,
     appstate: tauri::State<'_, AppState>,
-) -> Result<(), String> {
-    // Create a new Trie instance
-    let mut newtrie = Trie::new();
-    let config_path = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|_| "Failed to find appdata directory")?
-        .join("dataset");
-    // Parse all csv files under the path
+) -> Result<bool, String> {
+    if let Favorite::Alias { alias, value } = &favorite {
+        let trie = appstate.trie.read().unwrap();
+        find_pinned_candidate(&trie, alias, *value, None)
+            .ok_or_else(|| format!("Alias {} has no candidate {}", alias, value))?;
+    }
+    let added = appstate.favorites.write().unwrap().add(favorite);
+    if added {
+        appstate.invalidate_matches();
+        save_favorites(&app_handle, &appstate)?;
+    }
+    Ok(added)
+}
+
+/// Removes a favorite and saves the list
+/// Returns false if it was not a favorite
+#[tauri::command]
+fn remove_favorite(
+    favorite: Favorite,
+    app_handle: AppHandle,
+    appstate: tauri::State<'_, AppState>,
+) -> Result<bool, String> {
+    let removed = appstate.favorites.write().unwrap().remove(&favorite);
+    if removed {
+        appstate.invalidate_matches();
+        save_favorites(&app_handle, &appstate)?;
+    }
+    Ok(removed)
+}
+
+/// Inputs the favorite at the given position through keyboard
+#[tauri::command]
+fn select_favorite(
+    position: usize,
+    app_handle: AppHandle,
+    appstate: tauri::State<'_, AppState>,
+) -> bool {
+    let favorite = {
+        let trie = appstate.trie.read().unwrap();
+        let favorites = appstate.favorites.read().unwrap();
+        favorites
+            .items
+            .get(position)
+            .and_then(|f| resolve_favorite(&trie, f))
+    };
+    match favorite {
+        Some((_, _, value)) => insert_and_record(&app_handle, value),
+        None => {
+            log::info!("Favorite not found at position {}", position);
+            false
+        }
+    }
+}
+
+fn favorites_path(app_handle: &AppHandle) -> anyhow::Result<PathBuf> {
+    Ok(app_handle.path().app_data_dir()?.join("favorites.json"))
+}
+
+fn usage_path(app_handle: &AppHandle) -> anyhow::Result<PathBuf> {
+    Ok(app_handle.path().app_data_dir()?.join("usage.json"))
+}
+
+// Writes the favorites to the app data directory and rebuilds the tray menu
+fn save_favorites(app_handle: &AppHandle, appstate: &AppState) -> Result<(), String> {
+    let path = favorites_path(app_handle).map_err(|e| e.to_string())?;
+    appstate
+        .favorites
+        .read()
+        .unwrap()
+        .save(&path)
+        .map_err(|e| format!("Failed to save favorites: {}", e))?;
+    update_tray_menu(app_handle);
+    Ok(())
+}
+
+// Builds the tray menu. It lists the current favorites, recent characters and datasets
+fn build_tray_menu(app_handle: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let appstate = app_handle.state::<AppState>();
+    let settings = appstate.settings.read().unwrap();
+    let trie = appstate.trie.read().unwrap();
+    let favorites = appstate.favorites.read().unwrap();
+
+    let favorites_menu = Submenu::new(app_handle, "Favorites", true)?;
+    for m in favorite_matches(&trie, &favorites, None) {
+        let label = if m.matchstr.is_empty() {
+            m.value.to_string()
+        } else {
+            format!("{}  ({})", m.value, m.matchstr)
+        };
+        let id = format!("{}{}", FAVORITE_MENU_PREFIX, m.favorite.unwrap_or_default());
+        let item = MenuItem::with_id(app_handle, id, label, true, None::<&str>)?;
+        favorites_menu.append(&item)?;
+    }
+    if favorites_menu.items()?.is_empty() {
+        favorites_menu.append(&MenuItem::new(app_handle, "(empty)", false, None::<&str>)?)?;
+    }
+
+    // Each recent character can be inserted or copied
+    let recent_menu = Submenu::new(app_handle, "Recent Characters", true)?;
+    for (pos, ch) in appstate.recent.read().unwrap().iter().enumerate() {
+        let label = format!("{}  U+{:04X}", ch, *ch as u32);
+        let insert_id = format!("{}{}", RECENT_INSERT_MENU_PREFIX, pos);
+        let copy_id = format!("{}{}", RECENT_COPY_MENU_PREFIX, pos);
+        let item = Submenu::with_items(
+            app_handle,
+            label,
+            true,
+            &[
+                &MenuItem::with_id(app_handle, insert_id, "Insert", true, None::<&str>)?,
+                &MenuItem::with_id(app_handle, copy_id, "Copy", true, None::<&str>)?,
+            ],
+        )?;
+        recent_menu.append(&item)?;
+    }
+    if recent_menu.items()?.is_empty() {
+        recent_menu.append(&MenuItem::new(app_handle, "(empty)", false, None::<&str>)?)?;
+    }
+
+    // Checked datasets are loaded
+    let datasets_menu = Submenu::new(app_handle, "Datasets", true)?;
+    for name in dataset_names(app_handle).unwrap_or_default() {
+        let id = format!("{}{}", DATASET_MENU_PREFIX, name);
+        let enabled = !settings.disabled_datasets.contains(&name);
+        let item = CheckMenuItem::with_id(app_handle, id, &name, true, enabled, None::<&str>)?;
+        datasets_menu.append(&item)?;
+    }
+    if datasets_menu.items()?.is_empty() {
+        datasets_menu.append(&MenuItem::new(app_handle, "(empty)", false, None::<&str>)?)?;
+    }
+
+    let paused = *appstate.hotkeys_paused.read().unwrap();
+    let separator = PredefinedMenuItem::separator(app_handle)?;
+    Menu::with_items(
+        app_handle,
+        &[
+            &MenuItem::with_id(app_handle, "show", "Show", true, None::<&str>)?,
+            &separator,
+            &favorites_menu,
+            &recent_menu,
+            &separator,
+            &datasets_menu,
+            &MenuItem::with_id(app_handle, "reload", "Reload Datasets", true, None::<&str>)?,
+            &MenuItem::with_id(
+                app_handle,
+                "open_dataset_dir",
+                "Open Dataset Folder",
+                true,
+                None::<&str>,
+            )?,
+            &separator,
+            &CheckMenuItem::with_id(
+                app_handle,
+                "pause",
+                "Pause Hotkeys",
+                true,
+                paused,
+                None::<&str>,
+            )?,
+            &MenuItem::with_id(app_handle, "settings", "Settings", true, None::<&str>)?,
+            &separator,
+            &MenuItem::with_id(app_handle, "exit", "Exit", true, None::<&str>)?,
+        ],
+    )
+}
+
+// Shows and focuses the main window
+fn show_main_window(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+// Shows a system notification
+fn notify_user(app_handle: &AppHandle, body: &str) {
+    use tauri_plugin_notification::NotificationExt;
+    if let Err(e) = app_handle
+        .notification()
+        .builder()
+        .title("UniAlias")
+        .body(body)
+        .show()
+    {
+        log::info!("Failed to show notification: {}", e);
+    }
+}
+
+// Enables or disables a dataset from the tray menu, then reloads the datasets
+fn toggle_dataset(app_handle: &AppHandle, name: &str) {
+    let appstate = app_handle.state::<AppState>();
+    // A settings file that could not be loaded is not overwritten, until the settings window saves valid settings
+    if let Some(e) = appstate.settings_error.read().unwrap().clone() {
+        notify_user(
+            app_handle,
+            &format!(
+                "Datasets cannot be toggled until the settings file is fixed: {}",
+                e
+            ),
+        );
+        update_tray_menu(app_handle); // Revert the check mark
+        return;
+    }
+    let mut settings = appstate.settings.read().unwrap().clone();
+    if settings.disabled_datasets.iter().any(|d| d == name) {
+        settings.disabled_datasets.retain(|d| d != name);
+    } else {
+        settings.disabled_datasets.push(name.to_string());
+    }
+    if let Err(e) = settings_path(app_handle).and_then(|path| settings.save(&path)) {
+        log::error!("Failed to save settings: {}", e);
+        notify_user(app_handle, &format!("Failed to save settings: {}", e));
+        update_tray_menu(app_handle); // Revert the check mark
+        return;
+    }
+    *appstate.settings.write().unwrap() = settings;
+    appstate.invalidate_matches();
+    request_dataset_load(app_handle, true);
+}
+
+// Returns the recent character at the position given by a menu item id
+fn recent_char(app_handle: &AppHandle, position: &str) -> Option<char> {
+    let position = position.parse::<usize>().ok()?;
+    let appstate = app_handle.state::<AppState>();
+    let recent = appstate.recent.read().unwrap();
+    recent.get(position).copied()
+}
+
+// Copies a character to the clipboard
+fn copy_char(app_handle: &AppHandle, ch: char) {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+    if let Err(e) = app_handle.clipboard().write_text(ch.to_string()) {
+        log::info!("Failed to copy character: {}", e);
+    }
+}
+
+// Unregisters the hotkeys, or registers them again
+fn set_hotkeys_paused(app_handle: &AppHandle, paused: bool) {
+    let appstate = app_handle.state::<AppState>();
+    let Some(gs) = global_shortcut(app_handle) else {
+        log::info!("Hotkeys are unavailable");
+        return;
+    };
+    if paused {
+        if let Err(e) = gs.unregister_all() {
+            log::info!("Failed to unregister hotkeys: {}", e);
+        }
+    } else {
+        for hotkey in appstate.registered_hotkeys.read().unwrap().iter() {
+            if let Err(e) = gs.register(hotkey.as_str()) {
+                log::error!("Failed to register hotkey {:?}: {}", hotkey, e);
+            }
+        }
+    }
+    *appstate.hotkeys_paused.write().unwrap() = paused;
+    update_tray_menu(app_handle);
+}
+
+fn update_tray_menu(app_handle: &AppHandle) {
+    if let Some(tray) = app_handle.tray_by_id(TRAY_ID) {
+        if let Err(e) = build_tray_menu(app_handle).and_then(|menu| tray.set_menu(Some(menu))) {
+            log::error!("Error updating tray menu: {}", e);
+        }
+    }
+}
+
+/// Details of a candidate, shown in the preview panel
+#[derive(Serialize, Deserialize, Debug)]
+struct CandidateDetails {
+    alias: String,
+    value: char,
+    dataset: String,
+    info: CharInfo,
+}
+
+/// Returns the Unicode properties of a candidate of an alias and the dataset it came from
+/// index selects among the candidates of the alias (defaults to the first one)
+#[tauri::command]
+fn get_candidate_details(
+    alias: String,
+    index: Option<usize>,
+    appstate: tauri::State<'_, AppState>,
+) -> Result<CandidateDetails, String> {
+    let trie = appstate.trie.read().unwrap();
+    let candidate = find_candidate(&trie, &alias, index.unwrap_or(0)).map_err(|e| e.to_string())?;
+    Ok(CandidateDetails {
+        alias,
+        value: candidate.value,
+        dataset: trie.datasets[candidate.dataset].clone(),
+        info: CharInfo::new(candidate.value),
+    })
+}
+
+/// Starts reloading the datasets in the background
+///
+/// Progress is reported by "dataset_progress" events, and the result by a "dataset_loaded" event
+#[tauri::command]
+fn load_dataset(app_handle: tauri::AppHandle) {
+    request_dataset_load(&app_handle, false);
+}
+
+/// Progress of a dataset load, emitted as "dataset_progress" after each file
+#[derive(Serialize, Debug, Clone)]
+struct DatasetProgress {
+    file: String,
+    files_done: usize,
+    files_total: usize,
+    entries: usize,        // entries loaded so far
+    warnings: Vec<String>, // warnings of this file
+}
+
+/// Result of a dataset load, emitted as "dataset_loaded"
+#[derive(Serialize, Debug, Clone)]
+struct DatasetLoaded {
+    entries: usize,
+    warnings: usize,
+    error: Option<String>, // the previous datasets are kept if the load failed
+}
+
+/// Background dataset load. Requests made while loading are merged into one more load
+#[derive(Default)]
+struct DatasetLoadState {
+    running: bool,
+    pending: bool,
+    notify: bool, // show a notification when done
+}
+
+// Starts loading the datasets in the background, unless a load is already running
+// In that case they are loaded once more afterwards, so that the latest files and settings are used
+// Queries keep using the previous trie until the new one is swapped in
+fn request_dataset_load(app_handle: &AppHandle, notify: bool) {
+    let appstate = app_handle.state::<AppState>();
+    let mut load = appstate.dataset_load.lock().unwrap();
+    load.notify |= notify;
+    if load.running {
+        load.pending = true;
+        return;
+    }
+    load.running = true;
+    drop(load);
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || loop {
+        let appstate = app_handle.state::<AppState>();
+        // A panic while loading is reported like any other error, so that later loads can still run
+        let built = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            build_dataset_trie(&app_handle)
+        }))
+        .unwrap_or_else(|panic| Err(format!("Dataset load panicked: {}", panic_message(&panic))));
+        let loaded = match built {
+            Ok((trie, entries, warnings)) => {
+                *appstate.trie.write().unwrap() = trie;
+                appstate.invalidate_matches();
+                DatasetLoaded {
+                    entries,
+                    warnings,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e);
+                DatasetLoaded {
+                    entries: 0,
+                    warnings: 0,
+                    error: Some(e),
+                }
+            }
+        };
+        // Favorites and datasets in the tray menu depend on the loaded datasets
+        update_tray_menu(&app_handle);
+        if let Err(e) = app_handle.emit("dataset_loaded", &loaded) {
+            log::info!("Failed to emit dataset_loaded: {}", e);
+        }
+
+        let mut load = appstate.dataset_load.lock().unwrap();
+        if load.pending {
+            load.pending = false;
+            continue;
+        }
+        load.running = false;
+        let notify = std::mem::take(&mut load.notify);
+        drop(load);
+        if notify {
+            match &loaded.error {
+                None => notify_user(&app_handle, "Datasets reloaded"),
+                Some(e) => notify_user(&app_handle, &format!("Failed to reload datasets: {}", e)),
+            }
+        }
+        break;
+    });
+}
+
+// Returns the message of a panic payload, as passed to panic!
+fn panic_message(panic: &Box<dyn std::any::Any + Send>) -> &str {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s
+    } else {
+        "unknown error"
+    }
+}
+
+// Repeats the running dataset load (if any), since it may have read a dataset file before it changed
+fn invalidate_dataset_load(app_handle: &AppHandle) {
+    let appstate = app_handle.state::<AppState>();
+    let mut load = appstate.dataset_load.lock().unwrap();
+    if load.running {
+        load.pending = true;
+    }
+}
+
+// Loads all datasets under the "dataset" directory in the app data directory into a new trie
+// It expects each dataset to be in CSV format
+// Returns the trie and the numbers of entries and warnings, or an error if a dataset cannot be loaded or parsed
+fn build_dataset_trie(app_handle: &AppHandle) -> Result<(Trie, usize, usize), String> {
+    let appstate = app_handle.state::<AppState>();
+    let config_path = dataset_dir(app_handle)?;
+    let disabled = appstate.settings.read().unwrap().disabled_datasets.clone();
+    // List the csv files under the path first, to report the progress
     log::info!("Loading dataset from: {:?}...", config_path);
+    let mut files = Vec::new();
     for entry in std::fs::read_dir(config_path).map_err(|_| "Failed to open appdata directory")? {
         let entry = entry.map_err(|_| "Failed to read appdata entry")?;
         let path = entry.path();
-        let path = path.as_path();
 
         if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("csv") {
-            // Parse the unicode config file and append data to the trie
-            parse_unicode_dataset(path, &mut newtrie)
-                .map_err(|e| format!("Failed to parse dataset file {:?}: {}", path, e))?;
-            log::info!("Loaded dataset from: {:?}", path.file_name().unwrap());
+            if disabled.contains(&dataset_name(&path)) {
+                log::info!("Skipping disabled dataset: {:?}", path.file_name().unwrap());
+                continue;
+            }
+            files.push(path);
         } else {
             log::info!("Skipping non-csv file: {:?}", path);
         }
     }
+
+    // read_dir() gives no particular order. Sorting keeps the dataset order, and the cache key, stable
+    files.sort();
+
+    // Use the compiled trie if none of the files changed since it was cached
+    let cache_path = dataset_cache_path(app_handle)?;
+    let sources = match cache::load(&cache_path, &files) {
+        Ok((_, Some(cached))) => {
+            log::info!("Dataset loaded from cache: {:?}", cache_path);
+            return Ok((cached.trie, cached.entries, cached.warnings.len()));
+        }
+        Ok((sources, None)) => {
+            log::info!("Dataset cache is missing or stale. Rebuilding...");
+            Some(sources)
+        }
+        Err(e) => {
+            log::info!("Failed to read dataset cache: {}", e);
+            None
+        }
+    };
+
+    let mut entries = 0;
+    let mut warnings = Vec::new();
+    let newtrie = dataset::load_datasets(&files, |i, path, report| {
+        log::info!("Loaded dataset from: {:?}", path.file_name().unwrap());
+        for w in &report.warnings {
+            log::info!("Warning: {}", w);
+        }
+        entries += report.entries;
+        let progress = DatasetProgress {
+            file: dataset_name(path),
+            files_done: i + 1,
+            files_total: files.len(),
+            entries,
+            warnings: report.warnings,
+        };
+        if let Err(e) = app_handle.emit("dataset_progress", &progress) {
+            log::info!("Failed to emit dataset_progress: {}", e);
+        }
+        warnings.extend(progress.warnings);
+    })
+    .map_err(|e| e.to_string())?;
     log::info!("Dataset loaded successfully.");
 
-    // Swap the new trie into the ArcSwap
-    let mut triemut = appstate.trie.write().unwrap();
-    *triemut = newtrie;
-    Ok(())
+    let cached = cache::CachedDatasets {
+        trie: newtrie,
+        entries,
+        warnings,
+    };
+    if let Some(sources) = &sources {
+        if let Err(e) = cache::save(&cache_path, sources, &cached) {
+            log::info!("Failed to write dataset cache: {}", e);
+        }
+    }
+    Ok((cached.trie, cached.entries, cached.warnings.len()))
 }
 
-// //1) parse the unicode config file (a csv file of two colums. It contains comments starting with '#')
-// //2) appends all the parsed data into the trie
-fn parse_unicode_dataset(path: &Path, trie: &mut Trie) -> anyhow::Result<()> {
-    let file = File::open(path).map_err(|e| anyhow::anyhow!("Failed to open file: {}", e))?;
-    let reader = io::BufReader::new(file);
+// The compiled datasets are cached next to the dataset directory, not inside it
+fn dataset_cache_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to find appdata directory")?
+        .join("dataset_cache.bin"))
+}
 
-    for line in reader.lines() {
-        let line = line.map_err(|e| anyhow::anyhow!("Failed to read line: {}", e))?;
-        if line.len() > 0 && line.starts_with('#') {
-            // Skip comment lines
-            continue;
-        }
-        // Process each line as needed
-        if let Some(idx) = line.find(',') {
-            // Split the line into alias and character
-            let alias = line[..idx].trim().to_string();
-            // Check alias validity (non-empty, ASCII)
-            if alias.is_empty() {
-                return Err(anyhow::anyhow!(
-                    "Invalid line format. Alias string is empty: {}",
-                    line
-                ));
-            }
-            if !alias.is_ascii() {
-                return Err(anyhow::anyhow!(
-                    "Invalid line format. Alias string is not ASCII: {}",
-                    line
+/// An entry of the user dataset
+#[derive(Serialize, Deserialize, Debug)]
+struct UserAlias {
+    alias: String,
+    value: char,
+}
+
+fn user_dataset_path(app_handle: &AppHandle) -> anyhow::Result<PathBuf> {
+    Ok(app_handle
+        .path()
+        .app_data_dir()?
+        .join("dataset")
+        .join(format!("{}.csv", USER_DATASET)))
+}
+
+fn open_user_dataset(app_handle: &AppHandle) -> Result<UserDataset, String> {
+    user_dataset_path(app_handle)
+        .and_then(|path| UserDataset::open(&path))
+        .map_err(|e| format!("Failed to open user dataset: {}", e))
+}
+
+// Saves the user dataset. A dataset load in progress starts over, so that it reads the change
+fn save_user_dataset(app_handle: &AppHandle, user_dataset: &UserDataset) -> Result<(), String> {
+    user_dataset
+        .save()
+        .map_err(|e| format!("Failed to save user dataset: {}", e))?;
+    invalidate_dataset_load(app_handle);
+    Ok(())
+}
+
+// Applies a change of the user dataset to the loaded trie under one lock: removes the old entry and inserts the new one
+// The trie is left as is if the user dataset is disabled, as it was not loaded
+// Returns warnings about other datasets using the new alias, and about entries that could not be inserted
+fn update_user_entry(
+    appstate: &AppState,
+    namespace: Option<&str>,
+    old: Option<(&str, char)>,
+    new: Option<(String, char, Option<String>)>,
+) -> Vec<String> {
+    let settings = appstate.settings.read().unwrap();
+    let mut trie = appstate.trie.write().unwrap();
+    let mut warnings = Vec::new();
+    let user = trie.datasets.iter().position(|d| d == USER_DATASET);
+    if let Some((alias, _, _)) = &new {
+        if let Ok(candidates) = trie.find_value(alias) {
+            for c in candidates.iter().filter(|c| Some(c.dataset) != user) {
+                warnings.push(format!(
+                    "{} is also mapped to {} by dataset {}",
+                    alias, c.value, trie.datasets[c.dataset]
                 ));
             }
-
-            let ch = line[idx + 1..].trim().chars().next().ok_or_else(|| {
-                anyhow::anyhow!(
-                    "Invalid line format. No character found after comma in line: {}",
-                    line
-                )
-            })?;
-            if let Result::Err(e) = trie.append_leaf(alias, ch) {
-                log::info!("Warning: {}", e);
+        }
+    }
+    if settings.disabled_datasets.iter().any(|d| d == USER_DATASET) {
+        return warnings;
+    }
+    let dataset = user.unwrap_or_else(|| trie.add_dataset(USER_DATASET));
+    appstate.invalidate_matches();
+    if let Some((alias, value)) = old {
+        let mut aliases = vec![alias.to_string()];
+        if let Some(ns) = namespace {
+            aliases.push(format!("{}{}{}", ns, NAMESPACE_SEPARATOR, alias));
+        }
+        for a in aliases {
+            if let Err(e) = trie.remove_candidate(&a, value, dataset) {
+                log::info!("Warning: could not remove {} from the trie: {}", a, e);
             }
-        } else {
-            return Err(anyhow::anyhow!(
-                "Invalid line format. Comma separation not found: {}",
-                line
-            ));
         }
     }
+    if let Some((alias, value, description)) = new {
+        let description = description.as_deref();
+        for e in dataset::insert_entry(&mut trie, namespace, alias, value, description, dataset) {
+            warnings.push(e.to_string());
+        }
+    }
+    warnings
+}
+
+/// Lists the entries of the user dataset (`user.csv`)
+#[tauri::command]
+fn list_user_aliases(app_handle: AppHandle) -> Result<Vec<UserAlias>, String> {
+    Ok(open_user_dataset(&app_handle)?
+        .entries()
+        .into_iter()
+        .map(|(alias, value)| UserAlias { alias, value })
+        .collect())
+}
+
+/// Adds an entry to the user dataset and to the loaded trie
+///
+/// The entry is validated with the same rules as dataset files
+/// Returns warnings about other datasets using the same alias
+#[tauri::command]
+fn add_user_alias(
+    alias: String,
+    value: String,
+    app_handle: AppHandle,
+    appstate: tauri::State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let (alias, value) = dataset::parse_entry(&alias, &value).map_err(|e| e.to_string())?;
+    let mut user_dataset = open_user_dataset(&app_handle)?;
+    user_dataset.add(&alias, value).map_err(|e| e.to_string())?;
+    save_user_dataset(&app_handle, &user_dataset)?;
+    let namespace = user_dataset.namespace();
+    Ok(update_user_entry(
+        &appstate,
+        namespace.as_deref(),
+        None,
+        Some((alias, value, None)), // added entries have no description
+    ))
+}
+
+/// Removes an entry from the user dataset and from the loaded trie
+#[tauri::command]
+fn delete_user_alias(
+    alias: String,
+    value: char,
+    app_handle: AppHandle,
+    appstate: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    let mut user_dataset = open_user_dataset(&app_handle)?;
+    user_dataset
+        .remove(&alias, value)
+        .map_err(|e| e.to_string())?;
+    save_user_dataset(&app_handle, &user_dataset)?;
+    let namespace = user_dataset.namespace();
+    update_user_entry(&appstate, namespace.as_deref(), Some((&alias, value)), None);
     Ok(())
 }
 
+/// Replaces an entry of the user dataset in place. Returns the warnings of `add_user_alias`
+///
+/// The file is saved once, and the loaded trie is updated under one lock, so the old entry is never lost alone
+#[tauri::command]
+fn edit_user_alias(
+    old_alias: String,
+    old_value: char,
+    alias: String,
+    value: String,
+    app_handle: AppHandle,
+    appstate: tauri::State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let (new_alias, new_value) = dataset::parse_entry(&alias, &value).map_err(|e| e.to_string())?;
+    if new_alias == old_alias && new_value == old_value {
+        return Ok(Vec::new());
+    }
+    let mut user_dataset = open_user_dataset(&app_handle)?;
+    let description = user_dataset
+        .replace(&old_alias, old_value, &new_alias, new_value)
+        .map_err(|e| e.to_string())?;
+    save_user_dataset(&app_handle, &user_dataset)?;
+    let namespace = user_dataset.namespace();
+    Ok(update_user_entry(
+        &appstate,
+        namespace.as_deref(),
+        Some((&old_alias, old_value)),
+        Some((new_alias, new_value, description)),
+    ))
+}
+
+// Nested locks are always taken in the order of the fields below, to avoid deadlocks
+// A lock may be skipped, but never taken while holding a lock of a later field
 struct AppState {
+    settings: RwLock<AppSettings>,
+    settings_error: RwLock<Option<String>>, // why the settings file could not be loaded
+    hotkey_error: RwLock<Option<String>>,   // why some hotkeys could not be registered
+    registered_hotkeys: RwLock<Vec<String>>,
     trie: RwLock<Trie>,
+    favorites: RwLock<Favorites>,
+    usage: RwLock<Usage>,      // inserted characters, for frecency ordering
+    recent: RwLock<Vec<char>>, // recently inserted characters, most recent first
+    hotkeys_paused: RwLock<bool>,
+    match_cache: Mutex<Option<MatchCache>>,
+    dataset_load: Mutex<DatasetLoadState>,
 }
 
-fn setup_hotkey(app: &mut App, hotkey: &str) -> Result<(), Box<dyn std::error::Error>> {
+impl AppState {
+    // Drops the cached matches, as they depend on the trie, favorites, settings and usage
+    // Called after any of them changes, possibly while holding their locks
+    fn invalidate_matches(&self) {
+        *self.match_cache.lock().unwrap() = None;
+    }
+}
+
+/// Restricts the matches of the popup, as set by the hotkey that opened it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PopupScope {
+    dataset: Option<String>,
+    matching: Option<MatchMode>,
+}
+
+// Registers the global shortcut plugin. Shortcuts are registered afterwards by `change_hotkeys`
+fn setup_hotkey(app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
     use tauri_plugin_global_shortcut::{Builder, ShortcutState};
     app.handle().plugin(
         Builder::new()
-            .with_shortcuts([hotkey])?
-            .with_handler(move |_app, _, event| {
-                if event.state() == ShortcutState::Pressed {
-                    //log::info!("Shortcut Pressed: {:?}", shortcut);
-                    if let Err(e) = _app
-                        .get_webview_window("main")
-                        .unwrap()
-                        .emit("show_window", ())
-                    {
-                        log::info!("Error emitting event: {:?}", e);
+            .with_handler(move |_app, shortcut, event| {
+                //log::info!("Shortcut Pressed: {:?}", shortcut);
+                let appstate = _app.state::<AppState>();
+                let action = appstate
+                    .settings
+                    .read()
+                    .unwrap()
+                    .hotkeys
+                    .iter()
+                    .find(|b| b.hotkey.parse().ok().as_ref() == Some(shortcut))
+                    .map(|b| b.action.clone());
+                match (action, event.state()) {
+                    (Some(HotkeyAction::Popup { dataset, matching }), ShortcutState::Pressed) => {
+                        if let Err(e) = _app
+                            .get_webview_window("main")
+                            .unwrap()
+                            .emit("show_window", PopupScope { dataset, matching })
+                        {
+                            log::info!("Error emitting event: {:?}", e);
+                        }
+                    }
+                    // Characters are inserted on release with the modifiers of the hotkey released,
+                    // so that the keys of the hotkey do not interfere
+                    (Some(HotkeyAction::Repeat), ShortcutState::Released) => {
+                        let last = appstate.recent.read().unwrap().first().copied();
+                        match last {
+                            Some(ch) => {
+                                release_modifiers(shortcut);
+                                insert_and_record(_app, ch);
+                            }
+                            None => log::info!("Nothing to repeat"),
+                        }
                     }
+                    (Some(HotkeyAction::Alias { alias, index }), ShortcutState::Released) => {
+                        let candidate =
+                            find_candidate(&appstate.trie.read().unwrap(), &alias, index);
+                        match candidate {
+                            Ok(candidate) => {
+                                release_modifiers(shortcut);
+                                insert_and_record(_app, candidate.value);
+                            }
+                            Err(e) => log::info!("Error finding alias: {}", e),
+                        }
+                    }
+                    (None, _) => log::info!("Hotkey {:?} is not bound", shortcut),
+                    _ => {}
                 }
             })
             .build(),
@@ -194,12 +950,188 @@ fn setup_hotkey(app: &mut App, hotkey: &str) -> Result<(), Box<dyn std::error::E
     Result::Ok(())
 }
 
-fn load_settings(app: &App) -> anyhow::Result<HashMap<String, String>> {
-    let settings_path = app.path().app_data_dir()?.join("settings.json");
-    let file = File::open(settings_path)?;
-    let reader = io::BufReader::new(file);
-    let dict: HashMap<String, String> = serde_json::from_reader(reader)?;
-    return Ok(dict);
+// The global shortcut plugin, or None if `setup_hotkey` failed
+fn global_shortcut(
+    app_handle: &AppHandle,
+) -> Option<&tauri_plugin_global_shortcut::GlobalShortcut<Wry>> {
+    app_handle
+        .try_state::<tauri_plugin_global_shortcut::GlobalShortcut<Wry>>()
+        .map(|state| state.inner())
+}
+
+// Releases the modifiers of a hotkey. Its keys may still be held when it is released,
+// and a character typed meanwhile would be combined with them (ex. alt+α)
+fn release_modifiers(shortcut: &tauri_plugin_global_shortcut::Shortcut) {
+    use tauri_plugin_global_shortcut::Modifiers;
+    let Ok(mut en) = Enigo::new(&Settings::default()) else {
+        return;
+    };
+    for (modifier, key) in [
+        (Modifiers::SHIFT, Key::Shift),
+        (Modifiers::CONTROL, Key::Control),
+        (Modifiers::ALT, Key::Alt),
+        (Modifiers::SUPER | Modifiers::META, Key::Meta),
+    ] {
+        if shortcut.mods.intersects(modifier) {
+            if let Err(e) = en.key(key, Direction::Release) {
+                log::info!("Failed to release {:?}: {}", key, e);
+            }
+        }
+    }
+}
+
+/// Replaces the registered hotkeys `old` with `new`
+///
+/// If any of `new` cannot be registered (ex. it is already taken by another application),
+/// `old` is registered again and an error is returned
+fn change_hotkeys(app_handle: &AppHandle, old: &[String], new: &[String]) -> Result<(), String> {
+    use tauri_plugin_global_shortcut::Shortcut;
+    let mut new_shortcuts: Vec<(&String, Shortcut)> = Vec::new();
+    for hotkey in new {
+        let shortcut = hotkey
+            .parse()
+            .map_err(|e| format!("Invalid hotkey {:?}: {}", hotkey, e))?;
+        new_shortcuts.push((hotkey, shortcut));
+    }
+
+    let Some(gs) = global_shortcut(app_handle) else {
+        // Reported by `hotkey_error` at startup. The other settings can still be changed
+        log::info!("Hotkeys are unavailable, {:?} are not registered", new);
+        return Ok(());
+    };
+    if let Err(e) = gs.unregister_all() {
+        log::info!("Failed to unregister hotkeys: {}", e);
+    }
+    for (hotkey, shortcut) in new_shortcuts {
+        if let Err(e) = gs.register(shortcut) {
+            // Roll back to the previous hotkeys
+            if let Err(e) = gs.unregister_all() {
+                log::info!("Failed to unregister hotkeys: {}", e);
+            }
+            for old_hotkey in old {
+                if let Err(e) = gs.register(old_hotkey.as_str()) {
+                    log::error!("Failed to restore hotkey {:?}: {}", old_hotkey, e);
+                }
+            }
+            return Err(format!(
+                "Failed to register hotkey {:?}. It may be used by another application: {}",
+                hotkey, e
+            ));
+        }
+    }
+    log::info!("Registered hotkeys {:?}", new);
+    Ok(())
+}
+
+fn settings_path(app_handle: &AppHandle) -> anyhow::Result<PathBuf> {
+    Ok(app_handle.path().app_data_dir()?.join("settings.json"))
+}
+
+/// Returns the current settings (the defaults if the settings file could not be loaded)
+#[tauri::command]
+fn get_settings(appstate: tauri::State<'_, AppState>) -> AppSettings {
+    appstate.settings.read().unwrap().clone()
+}
+
+/// Returns why the settings file could not be loaded at startup, if it failed
+#[tauri::command]
+fn get_settings_error(appstate: tauri::State<'_, AppState>) -> Option<String> {
+    appstate.settings_error.read().unwrap().clone()
+}
+
+/// Returns why some hotkeys could not be registered at startup, if it failed
+#[tauri::command]
+fn get_hotkey_error(appstate: tauri::State<'_, AppState>) -> Option<String> {
+    appstate.hotkey_error.read().unwrap().clone()
+}
+
+/// Validates and saves the settings. Returns the validation errors, one per line
+/// Changes take effect immediately (paused hotkeys are resumed). If a new hotkey cannot be registered, nothing is saved
+#[tauri::command]
+fn save_settings(
+    settings: AppSettings,
+    app_handle: AppHandle,
+    appstate: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    let errors = settings.validate();
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    let path = settings_path(&app_handle).map_err(|e| e.to_string())?;
+
+    let old_hotkeys = appstate.registered_hotkeys.read().unwrap().clone();
+    let new_hotkeys: Vec<String> = settings.hotkeys.iter().map(|b| b.hotkey.clone()).collect();
+    change_hotkeys(&app_handle, &old_hotkeys, &new_hotkeys)?;
+
+    if let Err(e) = settings.save(&path) {
+        // Keep the hotkeys consistent with the settings file
+        if let Err(e) = change_hotkeys(&app_handle, &new_hotkeys, &old_hotkeys) {
+            log::error!("Failed to restore hotkeys: {}", e);
+        }
+        return Err(format!("Failed to save settings: {}", e));
+    }
+    *appstate.settings.write().unwrap() = settings;
+    *appstate.settings_error.write().unwrap() = None;
+    appstate.invalidate_matches();
+    if global_shortcut(&app_handle).is_some() {
+        *appstate.registered_hotkeys.write().unwrap() = new_hotkeys;
+        *appstate.hotkey_error.write().unwrap() = None;
+        // The new hotkeys are registered, so they are no longer paused
+        *appstate.hotkeys_paused.write().unwrap() = false;
+    }
+    update_tray_menu(&app_handle);
+    Ok(())
+}
+
+/// Returns whether the app is started at login
+#[tauri::command]
+fn get_autostart() -> Result<bool, String> {
+    autostart::is_enabled().map_err(|e| format!("Failed to read the autostart entry: {}", e))
+}
+
+/// Starts the app at login (in the system tray), or stops doing so
+#[tauri::command]
+fn set_autostart(enabled: bool) -> Result<(), String> {
+    autostart::set_enabled(enabled)
+        .map_err(|e| format!("Failed to update the autostart entry: {}", e))
+}
+
+/// Lists the names of all datasets in the dataset directory, including disabled ones
+#[tauri::command]
+fn list_datasets(app_handle: AppHandle) -> Result<Vec<String>, String> {
+    dataset_names(&app_handle)
+}
+
+/// Checks all datasets in the dataset directory, including disabled ones
+#[tauri::command]
+fn lint_datasets(app_handle: AppHandle) -> Result<Vec<lint::LintIssue>, String> {
+    let dir = dataset_dir(&app_handle)?;
+    let files: Vec<PathBuf> = dataset_names(&app_handle)?
+        .iter()
+        .map(|name| dir.join(format!("{}.csv", name)))
+        .collect();
+    lint::lint_datasets(&dir, &files).map_err(|e| e.to_string())
+}
+
+fn dataset_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|_| "Failed to find appdata directory")?
+        .join("dataset"))
+}
+
+fn dataset_names(app_handle: &AppHandle) -> Result<Vec<String>, String> {
+    let dataset_dir = dataset_dir(app_handle)?;
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dataset_dir).map_err(|_| "Failed to open dataset directory")? {
+        let path = entry.map_err(|_| "Failed to read dataset entry")?.path();
+        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("csv") {
+            names.push(dataset_name(&path));
+        }
+    }
+    names.sort();
+    Ok(names)
 }
 
 fn copy_files(from_dir : &PathBuf, to_dir : &PathBuf, ext_whitelist : &[&str]) -> anyhow::Result<()> {
@@ -239,7 +1171,7 @@ pub fn run() {
     }
 
     let args: Vec<String> = std::env::args().collect();
-    let hidden = args.iter().any(|arg| arg == "--hidden");
+    let hidden = args.iter().any(|arg| arg == autostart::HIDDEN_ARG);
 
     tauri::Builder::default()
         .plugin(
@@ -253,14 +1185,23 @@ pub fn run() {
                 .build(),
         )
         .plugin(tauri_plugin_single_instance::init(|app, _, _| {
-            if let Some(window) = app.get_webview_window("main") {
-                let _ = window.show();
-                let _ = window.set_focus();
-            }
+            show_main_window(app);
         }))
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(AppState {
             trie: RwLock::new(Trie::new()),
+            settings: RwLock::new(AppSettings::default()),
+            settings_error: RwLock::new(None),
+            hotkey_error: RwLock::new(None),
+            registered_hotkeys: RwLock::new(Vec::new()),
+            recent: RwLock::new(Vec::new()),
+            hotkeys_paused: RwLock::new(false),
+            dataset_load: Mutex::new(DatasetLoadState::default()),
+            favorites: RwLock::new(Favorites::default()),
+            usage: RwLock::new(Usage::default()),
+            match_cache: Mutex::new(None),
         })
         .setup(move |app| {
             //Move Datasets if dataset folder not exists in appdata
@@ -279,17 +1220,35 @@ pub fn run() {
             }
 
             // Open settings
-            let settings_dict = match load_settings(app) {
-                Ok(dict) => dict,
+            let settings = match settings_path(app.handle())
+                .and_then(|path| AppSettings::load(&path))
+            {
+                Ok(settings) => settings,
                 Err(e) => {
                     log::error!("Error loading settings: {}", e);
-                    HashMap::new()
+                    *app.state::<AppState>().settings_error.write().unwrap() = Some(e.to_string());
+                    AppSettings::default()
                 }
             };
+            // Load favorites
+            match favorites_path(app.handle()).and_then(|path| Favorites::load(&path)) {
+                Ok(favorites) => *app.state::<AppState>().favorites.write().unwrap() = favorites,
+                Err(e) => log::error!("Error loading favorites: {}", e),
+            }
+            // Load the usage history
+            match usage_path(app.handle()).and_then(|path| Usage::load(&path)) {
+                Ok(usage) => *app.state::<AppState>().usage.write().unwrap() = usage,
+                Err(e) => log::error!("Error loading usage history: {}", e),
+            }
+            let hotkeys = settings.hotkeys.clone();
+            // The tray menu lists the datasets according to the settings
+            *app.state::<AppState>().settings.write().unwrap() = settings;
+            // Load the datasets in the background
+            request_dataset_load(app.handle(), false);
+
             // Register the tray icon
-            let exit_i = MenuItem::with_id(app, "exit", "Exit", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&exit_i])?;
-            let _ = TrayIconBuilder::new()
+            let menu = build_tray_menu(app.handle())?;
+            let _ = TrayIconBuilder::with_id(TRAY_ID)
                 .tooltip("UniAlias")
                 .menu(&menu)
                 .on_tray_icon_event(|tray, event| match event {
@@ -298,24 +1257,39 @@ pub fn run() {
                         button_state: MouseButtonState::Up,
                         ..
                     } => {
-                        let app = tray.app_handle();
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        }
+                        show_main_window(tray.app_handle());
                     }
                     _ => {}
                 })
                 .icon(app.default_window_icon().unwrap().clone())
                 .build(app)?;
 
-            let hotkey: &str = settings_dict
-                .get("hotkey")
-                .map(|s| s.as_str())
-                .unwrap_or_else(|| "alt+shift+u");
             // Setup the hotkey
-            if let Err(e) = setup_hotkey(app, hotkey) {
+            let mut errors = Vec::new();
+            if let Err(e) = setup_hotkey(app) {
                 log::error!("Error setting up hotkey: {}", e);
+                errors.push(format!("Hotkeys are unavailable: {}", e));
+            }
+            // Register each hotkey separately, so that one taken hotkey does not disable the others
+            let mut registered = Vec::new();
+            if let Some(gs) = global_shortcut(app.handle()) {
+                for binding in &hotkeys {
+                    match gs.register(binding.hotkey.as_str()) {
+                        Ok(()) => registered.push(binding.hotkey.clone()),
+                        Err(e) => {
+                            log::error!("Error registering hotkey {:?}: {}", binding.hotkey, e);
+                            errors.push(format!(
+                                "Failed to register hotkey {:?}: {}",
+                                binding.hotkey, e
+                            ));
+                        }
+                    }
+                }
+            }
+            let appstate = app.state::<AppState>();
+            *appstate.registered_hotkeys.write().unwrap() = registered;
+            if !errors.is_empty() {
+                *appstate.hotkey_error.write().unwrap() = Some(errors.join("\n"));
             }
 
             // If the "--hidden" argument is not provided, show the main window
@@ -341,6 +1315,57 @@ pub fn run() {
                 log::info!("Exit...");
                 app.exit(0);
             }
+            "show" => show_main_window(app),
+            "reload" => request_dataset_load(app, true),
+            "open_dataset_dir" => {
+                use tauri_plugin_opener::OpenerExt;
+                match dataset_dir(app) {
+                    Ok(dir) => {
+                        if let Err(e) = app.opener().open_path(dir.to_string_lossy(), None::<&str>)
+                        {
+                            log::error!("Failed to open dataset folder: {}", e);
+                        }
+                    }
+                    Err(e) => log::error!("{}", e),
+                }
+            }
+            "settings" => {
+                // Windows are created by the main window (see main.js)
+                if let Some(window) = app.get_webview_window("main") {
+                    if let Err(e) = window.emit("open_window", "settings") {
+                        log::error!("Failed to open settings: {}", e);
+                    }
+                }
+            }
+            "pause" => {
+                let paused = *app.state::<AppState>().hotkeys_paused.read().unwrap();
+                set_hotkeys_paused(app, !paused);
+            }
+            id if id.starts_with(FAVORITE_MENU_PREFIX) => {
+                match id[FAVORITE_MENU_PREFIX.len()..].parse::<usize>() {
+                    Ok(position) => {
+                        select_favorite(position, app.clone(), app.state::<AppState>());
+                    }
+                    Err(e) => log::info!("Invalid favorite menu id {:?}: {}", id, e),
+                }
+            }
+            id if id.starts_with(RECENT_INSERT_MENU_PREFIX) => {
+                match recent_char(app, &id[RECENT_INSERT_MENU_PREFIX.len()..]) {
+                    Some(ch) => {
+                        insert_and_record(app, ch);
+                    }
+                    None => log::info!("Invalid recent character menu id {:?}", id),
+                }
+            }
+            id if id.starts_with(RECENT_COPY_MENU_PREFIX) => {
+                match recent_char(app, &id[RECENT_COPY_MENU_PREFIX.len()..]) {
+                    Some(ch) => copy_char(app, ch),
+                    None => log::info!("Invalid recent character menu id {:?}", id),
+                }
+            }
+            id if id.starts_with(DATASET_MENU_PREFIX) => {
+                toggle_dataset(app, &id[DATASET_MENU_PREFIX.len()..]);
+            }
             _ => {
                 log::info!("menu item {:?} not handled", event.id);
             }
@@ -349,6 +1374,23 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             find_matches,
             select_alias,
+            get_candidate_details,
+            list_favorites,
+            add_favorite,
+            remove_favorite,
+            select_favorite,
+            list_user_aliases,
+            add_user_alias,
+            edit_user_alias,
+            delete_user_alias,
+            get_settings,
+            get_settings_error,
+            get_hotkey_error,
+            get_autostart,
+            set_autostart,
+            save_settings,
+            list_datasets,
+            lint_datasets,
             load_dataset
         ])
         .run(tauri::generate_context!())
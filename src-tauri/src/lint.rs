@@ -0,0 +1,400 @@
+use crate::dataset::{dataset_name, parse_line, parse_value, split_entry, DatasetLine};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_normalization::UnicodeNormalization;
+
+/// How serious a lint issue is
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    /// The line is skipped, or the entry cannot be typed
+    Error,
+}
+
+/// A check of dataset entries
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    Syntax,
+    Whitespace,
+    TrailingJunk,
+    Duplicate,
+    Invisible,
+    Confusable,
+    Unassigned,
+    NonNfc,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 8] = [
+        Rule::Syntax,
+        Rule::Whitespace,
+        Rule::TrailingJunk,
+        Rule::Duplicate,
+        Rule::Invisible,
+        Rule::Confusable,
+        Rule::Unassigned,
+        Rule::NonNfc,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Syntax => "syntax",
+            Rule::Whitespace => "whitespace",
+            Rule::TrailingJunk => "trailing-junk",
+            Rule::Duplicate => "duplicate",
+            Rule::Invisible => "invisible",
+            Rule::Confusable => "confusable",
+            Rule::Unassigned => "unassigned",
+            Rule::NonNfc => "non-nfc",
+        }
+    }
+
+    pub fn severity(self) -> Severity {
+        match self {
+            Rule::Syntax | Rule::Unassigned => Severity::Error,
+            Rule::Confusable => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::Syntax => {
+                "The line is not a comment, a namespace directive or an `alias,character` entry"
+            }
+            Rule::Whitespace => "Whitespace around or inside the alias, or around the character",
+            Rule::TrailingJunk => {
+                "More than one character after the comma (only the first one is used)"
+            }
+            Rule::Duplicate => {
+                "The same alias and character are defined again, in the same or another file"
+            }
+            Rule::Invisible => {
+                "The character is invisible, a control or a bidirectional formatting character"
+            }
+            Rule::Confusable => "The character looks like a different character of another entry",
+            Rule::Unassigned => "The codepoint is not assigned to any character",
+            Rule::NonNfc => {
+                "The character changes under NFC normalization, so it may not survive copying"
+            }
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem found in a dataset file
+///
+/// Displayed as `<dataset> <file>:<line>: <severity> [<rule>] <message>`, the report format of every frontend
+#[derive(Serialize, Debug, Clone)]
+pub struct LintIssue {
+    pub rule: Rule,
+    pub severity: Severity,
+    /// Name of the dataset (see `dataset_name`)
+    pub dataset: String,
+    /// Path of the file, relative to the directory it was found in
+    pub file: String,
+    /// 1-based line number
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}:{}: {} [{}] {}",
+            self.dataset,
+            self.file,
+            self.line,
+            self.severity,
+            self.rule.name(),
+            self.message
+        )
+    }
+}
+
+// Where an entry is defined
+#[derive(Clone)]
+struct Location {
+    file: String,
+    line: usize,
+}
+
+/// Checks dataset files, one after another
+///
+/// Duplicates and confusables are also reported across files, against the first file defining them
+#[derive(Default)]
+pub struct Linter {
+    issues: Vec<LintIssue>,
+    entries: HashMap<(String, char), Location>,
+    // First character of each confusable skeleton
+    skeletons: HashMap<String, (char, Location)>,
+}
+
+impl Linter {
+    /// Checks a dataset file found in dir. The issues name it by its path relative to dir
+    ///
+    /// An empty dir keeps the path as it is
+    pub fn lint_file(&mut self, dir: &Path, path: &Path) -> anyhow::Result<()> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", path, e))?;
+        let file = path.strip_prefix(dir).unwrap_or(path);
+        self.lint_str(&file.display().to_string(), &text);
+        Ok(())
+    }
+
+    /// Checks the text of a dataset file, named file in the issues
+    pub fn lint_str(&mut self, file: &str, text: &str) {
+        for (i, line) in text.lines().enumerate() {
+            self.lint_line(file, i + 1, line);
+        }
+    }
+
+    /// Returns the issues, sorted by file and line
+    pub fn finish(mut self) -> Vec<LintIssue> {
+        // Stable, so the issues of a line keep the order of the rules
+        self.issues
+            .sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        self.issues
+    }
+
+    fn report(&mut self, rule: Rule, file: &str, line: usize, message: String) {
+        self.issues.push(LintIssue {
+            rule,
+            severity: rule.severity(),
+            dataset: dataset_name(Path::new(file)),
+            file: file.to_string(),
+            line,
+            message,
+        });
+    }
+
+    fn lint_line(&mut self, file: &str, line_no: usize, line: &str) {
+        let (alias, value) = match parse_line(line) {
+            Ok(DatasetLine::Entry { alias, value, .. }) => (alias, value),
+            Ok(_) => return,
+            Err(e) => {
+                self.report(Rule::Syntax, file, line_no, e.to_string());
+                return;
+            }
+        };
+        // parse_line trims the columns, check the raw ones
+        let Ok(columns) = split_entry(line) else {
+            return;
+        };
+        if columns.alias != alias || alias.contains(|c: char| c.is_ascii_whitespace()) {
+            self.report(
+                Rule::Whitespace,
+                file,
+                line_no,
+                format!("Alias {:?} contains whitespace", columns.alias),
+            );
+        }
+        // Quoted values are taken as they are
+        let rest = if columns.value_quoted {
+            columns.value.as_str()
+        } else {
+            columns.value.trim_start()
+        };
+        if rest.len() != columns.value.len() {
+            self.report(
+                Rule::Whitespace,
+                file,
+                line_no,
+                format!("Whitespace before the character of {}", alias),
+            );
+        }
+        let junk = parse_value(rest).map(|(_, junk)| junk).unwrap_or_default();
+        if junk.trim().is_empty() && !columns.value_quoted {
+            if !junk.is_empty() {
+                self.report(
+                    Rule::Whitespace,
+                    file,
+                    line_no,
+                    format!("Whitespace after the character of {}", alias),
+                );
+            }
+        } else if !junk.is_empty() {
+            self.report(
+                Rule::TrailingJunk,
+                file,
+                line_no,
+                format!("{:?} after the character of {} is ignored", junk, alias),
+            );
+        }
+
+        self.lint_value(file, line_no, &alias, value);
+
+        match self.entries.get(&(alias.clone(), value)) {
+            Some(first) => {
+                let message = format!(
+                    "{} -> {} is already defined at {}:{}",
+                    alias, value, first.file, first.line
+                );
+                self.report(Rule::Duplicate, file, line_no, message);
+            }
+            None => {
+                let here = Location {
+                    file: file.to_string(),
+                    line: line_no,
+                };
+                self.entries.insert((alias, value), here);
+            }
+        }
+    }
+
+    // Checks the character of an entry
+    fn lint_value(&mut self, file: &str, line_no: usize, alias: &str, value: char) {
+        let codepoint = format!("U+{:04X}", value as u32);
+        match get_general_category(value) {
+            GeneralCategory::Unassigned => {
+                let message = format!("{} of {} is not assigned", codepoint, alias);
+                self.report(Rule::Unassigned, file, line_no, message);
+            }
+            GeneralCategory::Format
+            | GeneralCategory::Control
+            | GeneralCategory::LineSeparator
+            | GeneralCategory::ParagraphSeparator => {
+                let message = format!("{} of {} is invisible", codepoint, alias);
+                self.report(Rule::Invisible, file, line_no, message);
+            }
+            _ => {}
+        }
+
+        let nfc: String = value.nfc().collect();
+        if nfc != value.to_string() {
+            let message = format!(
+                "{} of {} normalizes to {} ({})",
+                codepoint,
+                alias,
+                nfc,
+                nfc.chars()
+                    .map(|c| format!("U+{:04X}", c as u32))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+            self.report(Rule::NonNfc, file, line_no, message);
+        }
+
+        // Styled variants (e.g. mathematical alphanumerics) look alike on purpose
+        let value_str = value.to_string();
+        if value_str.nfkc().ne(value_str.chars()) {
+            return;
+        }
+        let skeleton: String = unicode_security::skeleton(&value_str).collect();
+        match self.skeletons.get(&skeleton) {
+            Some((other, first)) if *other != value => {
+                let message = format!(
+                    "{} ({}) of {} looks like {} (U+{:04X}) at {}:{}",
+                    value, codepoint, alias, other, *other as u32, first.file, first.line
+                );
+                self.report(Rule::Confusable, file, line_no, message);
+            }
+            Some(_) => {}
+            None => {
+                let here = Location {
+                    file: file.to_string(),
+                    line: line_no,
+                };
+                self.skeletons.insert(skeleton, (value, here));
+            }
+        }
+    }
+}
+
+/// Checks the given dataset files of dir in the given order
+pub fn lint_datasets(dir: &Path, files: &[PathBuf]) -> anyhow::Result<Vec<LintIssue>> {
+    let mut linter = Linter::default();
+    for path in files {
+        linter.lint_file(dir, path)?;
+    }
+    Ok(linter.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(files: &[(&str, &str)]) -> Vec<(Rule, usize)> {
+        let mut linter = Linter::default();
+        for (file, text) in files {
+            linter.lint_str(file, text);
+        }
+        linter
+            .finish()
+            .iter()
+            .map(|issue| (issue.rule, issue.line))
+            .collect()
+    }
+
+    #[test]
+    fn clean_dataset() {
+        let text = "# Greek letters\nalpha,α\nbeta,β\n";
+        assert_eq!(rules(&[("greek", text)]), vec![]);
+    }
+
+    #[test]
+    fn entry_rules() {
+        let text =
+            "alpha ,α\nbeta, β\ngamma,γγ\nnoalias\nzwj,\u{200D}\nnone,\u{0378}\nangstrom,\u{212B}";
+        assert_eq!(
+            rules(&[("greek", text)]),
+            vec![
+                (Rule::Whitespace, 1),
+                (Rule::Whitespace, 2),
+                (Rule::TrailingJunk, 3),
+                (Rule::Syntax, 4),
+                (Rule::Invisible, 5),
+                (Rule::Unassigned, 6),
+                (Rule::NonNfc, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn rules_across_files() {
+        let greek = "alpha,α\nOmicron,Ο";
+        let latin = "alpha,α\nO,O\nbold_O,𝐎";
+        assert_eq!(
+            rules(&[("greek", greek), ("latin", latin)]),
+            vec![(Rule::Duplicate, 1), (Rule::Confusable, 2)]
+        );
+    }
+
+    #[test]
+    fn issues_name_the_dataset_and_relative_file() {
+        let dir = std::env::temp_dir().join(format!("unialias-lint-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let files = [dir.join("sub").join("greek.csv")];
+        fs::write(&files[0], "alpha,α\nalpha,α\n").unwrap();
+        let issues = lint_datasets(&dir, &files);
+        let _ = fs::remove_dir_all(&dir);
+        let issues = issues.unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].dataset, "greek");
+        let file = Path::new("sub").join("greek.csv").display().to_string();
+        assert_eq!(
+            issues[0].to_string(),
+            format!(
+                "greek {}:2: warning [duplicate] {}",
+                file, issues[0].message
+            )
+        );
+    }
+}
@@ -0,0 +1,1049 @@
+use crate::dataset::NAMESPACE_SEPARATOR;
+use crate::favorites::{Favorite, Favorites};
+use crate::settings::{MatchMode, MatchOrder};
+use crate::trie::{normalize_alias, Candidate, Trie};
+use crate::usage::{self, Usage};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+/// A candidate matching the input, as listed in the popup
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatchData {
+    pub matchstr: String,
+    pub matchstart: usize, // 0 for prefix matches
+    pub matchlen: usize,
+    pub value: char,
+    pub index: usize, // index of the candidate among the values of the alias
+    pub favorite: Option<usize>, // position in the favorites list
+    pub score: u32,   // see `match_score`, 0 for favorites listed without input
+    pub distance: usize, // number of typos corrected, 0 unless listed by `find_corrections`
+    pub description: Option<String>, // see `Trie::description`
+}
+
+/// How matches are ranked within the prefix and infix matches (see `MatchOrder`)
+pub struct Ranking<'a> {
+    pub order: MatchOrder,
+    /// Rank of each dataset of the trie, by index (lower first)
+    pub dataset_ranks: Vec<usize>,
+    /// Usage history for `MatchOrder::Frecency`
+    pub usage: &'a Usage,
+    /// Current time in seconds since the UNIX epoch
+    pub now: u64,
+}
+
+impl Ranking<'_> {
+    /// Builds the ranking of an order
+    ///
+    /// `priority` lists dataset names in order of priority, unlisted datasets keep their order after them
+    pub fn new<'a>(
+        order: MatchOrder,
+        trie: &Trie,
+        priority: &[String],
+        usage: &'a Usage,
+    ) -> Ranking<'a> {
+        let dataset_ranks = trie
+            .datasets
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                priority
+                    .iter()
+                    .position(|p| p == name)
+                    .unwrap_or(priority.len() + i)
+            })
+            .collect();
+        Ranking {
+            order,
+            dataset_ranks,
+            usage,
+            now: usage::now(),
+        }
+    }
+
+    // Rank of a dataset (lower first). Unknown datasets come last
+    fn dataset_rank(&self, dataset: usize) -> usize {
+        self.dataset_ranks
+            .get(dataset)
+            .copied()
+            .unwrap_or(usize::MAX)
+    }
+
+    // Rank of a candidate (lower first)
+    fn rank(&self, alias: &str, candidate: &Candidate, score: u32) -> u64 {
+        match self.order {
+            MatchOrder::Score => (u32::MAX - score) as u64,
+            MatchOrder::Lexicographic => 0,
+            MatchOrder::Shortest => alias.len() as u64,
+            MatchOrder::Dataset => self.dataset_rank(candidate.dataset) as u64,
+            MatchOrder::Frecency => u64::MAX - self.usage.frecency(candidate.value, self.now),
+        }
+    }
+}
+
+/// Scores how well an alias matches the input (higher is better)
+///
+/// The match is the part of the alias from mstart of length mlen. In order of weight, the score favors:
+/// - an exact match of the whole alias
+/// - a match ending at a word boundary (`_` or the end of the alias, ex. `in` in `in_set`)
+/// - a match starting at a word boundary (the start of the alias or after `_`)
+/// - shorter aliases
+/// - datasets of higher priority (lower rank)
+pub fn match_score(alias: &str, mstart: usize, mlen: usize, dataset_rank: usize) -> u32 {
+    let alias = alias.as_bytes();
+    let end = mstart + mlen;
+    let mut score = 0;
+    if mstart == 0 && end == alias.len() {
+        score += 2000;
+    } else if alias.get(end).is_none_or(|&b| b == b'_') {
+        score += 1000;
+    }
+    if mstart == 0 || alias.get(mstart - 1) == Some(&b'_') {
+        score += 500;
+    }
+    // Each byte of length outweighs the dataset priority
+    score += (24 - alias.len().min(24) as u32) * 16;
+    score += 15 - dataset_rank.min(15) as u32;
+    score
+}
+
+// Sort key of a match: infix matches last, then closer corrections first, then favorites first,
+// then exact matches first, then the rank, then the order in which it was found
+type MatchKey = (bool, usize, bool, bool, u64, usize);
+
+// A match ordered by its key only
+struct Ranked(MatchKey, MatchData);
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+/// How the prefix matches relate to the input
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    /// The listed aliases start with the whole input (or there is no input)
+    Full,
+    /// No alias starts with the whole input. Aliases starting with its longest matching part are listed
+    Partial,
+    /// No alias starts with the whole input. Aliases starting with a correction of it are listed ("did you mean")
+    Corrected,
+    /// No alias starts with the whole input. Candidates whose description matches it are listed
+    Description,
+}
+
+/// The best matches of an input, along with the number of all matches
+#[derive(Debug)]
+pub struct Matches {
+    pub matches: Vec<MatchData>,
+    pub total: usize,
+    pub kind: MatchKind,
+}
+
+/// The best matches of the last query (input, dataset, matching mode), so that its further pages are not searched for again
+///
+/// A query is searched for the best (offset + cnt) matches. The first page is always searched again,
+/// while further pages of the same query are sliced from the last search. If it is too short,
+/// the query is searched for at least twice as many matches, so that listing n matches page by page
+/// takes a number of searches logarithmic in n
+pub struct MatchCache {
+    pub query: (String, Option<usize>, MatchMode),
+    pub limit: usize, // number of best matches searched for
+    pub result: Matches,
+}
+
+impl MatchCache {
+    /// Returns the number of matches to search for to list (cnt) matches after (offset),
+    /// or None if they are in the cache
+    pub fn search_limit(
+        cache: Option<&MatchCache>,
+        query: &(String, Option<usize>, MatchMode),
+        offset: usize,
+        cnt: usize,
+    ) -> Option<usize> {
+        let needed = offset.saturating_add(cnt);
+        match cache {
+            Some(c) if offset > 0 && c.query == *query => {
+                if c.limit >= needed || c.result.total <= c.limit {
+                    None
+                } else {
+                    Some(needed.max(c.limit.saturating_mul(2)))
+                }
+            }
+            _ => Some(needed),
+        }
+    }
+}
+
+// Keeps the best (limit) candidates of the aliases pushed into it
+struct Collector<'a> {
+    trie: &'a Trie,
+    favorites: &'a Favorites,
+    dataset: Option<usize>,
+    ranking: &'a Ranking<'a>,
+    limit: usize,
+    qualified: bool,                 // whether namespace-qualified aliases are listed
+    pushed: HashSet<(usize, usize)>, // (alias idx, candidate index)
+    // Max-heap of the best matches so far, the worst kept match is on top
+    best: BinaryHeap<Ranked>,
+    total: usize,
+}
+
+impl<'a> Collector<'a> {
+    fn new(
+        trie: &'a Trie,
+        favorites: &'a Favorites,
+        input: &str,
+        dataset: Option<usize>,
+        ranking: &'a Ranking<'a>,
+        limit: usize,
+    ) -> Self {
+        Collector {
+            trie,
+            favorites,
+            dataset,
+            ranking,
+            limit,
+            qualified: input.contains(NAMESPACE_SEPARATOR),
+            pushed: HashSet::new(),
+            best: BinaryHeap::new(),
+            total: 0,
+        }
+    }
+
+    // Pushes the candidates of an alias. distance is 0 unless the alias is a spelling correction
+    fn push_leaf(&mut self, idx: usize, mstart: usize, mlen: usize, distance: usize) {
+        let Some(data) = self.trie.candidates(idx) else {
+            return;
+        };
+        for index in 0..data.len() {
+            self.push_candidate(idx, index, mstart, mlen, distance);
+        }
+    }
+
+    // Pushes a candidate of an alias, unless its alias or dataset is not listed or it was already pushed
+    fn push_candidate(
+        &mut self,
+        idx: usize,
+        index: usize,
+        mstart: usize,
+        mlen: usize,
+        distance: usize,
+    ) {
+        let Some(ch) = self.trie.candidates(idx).and_then(|data| data.get(index)) else {
+            return;
+        };
+        let alias = self.trie.value_str(idx);
+        if !self.qualified && alias.contains(NAMESPACE_SEPARATOR) {
+            return;
+        }
+        if self.dataset.is_some_and(|d| d != ch.dataset) || !self.pushed.insert((idx, index)) {
+            return;
+        }
+        let favorite = self.favorites.position_of_alias(alias, ch.value);
+        let score = match_score(alias, mstart, mlen, self.ranking.dataset_rank(ch.dataset));
+        let key = (
+            mstart > 0,
+            distance,
+            favorite.is_none(),
+            mstart != 0 || mlen != alias.len(),
+            self.ranking.rank(alias, ch, score),
+            self.total,
+        );
+        self.total += 1;
+        if self.best.len() == self.limit && self.best.peek().is_none_or(|worst| key >= worst.0) {
+            return;
+        }
+        self.best.push(Ranked(
+            key,
+            MatchData {
+                matchstr: alias.to_owned(),
+                matchstart: mstart,
+                matchlen: mlen,
+                value: ch.value,
+                index,
+                favorite,
+                score,
+                distance,
+                description: self.trie.description(ch).map(|d| d.to_owned()),
+            },
+        ));
+        if self.best.len() > self.limit {
+            self.best.pop();
+        }
+    }
+
+    // Pushes the aliases containing the input after their first character.
+    // A namespace in the input only restricts the dataset
+    fn push_infix_matches(&mut self, input: &str) {
+        let (ns_prefix, infix) = match input.rfind(NAMESPACE_SEPARATOR) {
+            Some(pos) => input.split_at(pos + 1),
+            None => ("", input),
+        };
+        for (idx, mstart) in self.trie.find_infix_matches(infix.as_bytes()) {
+            if !self.trie.value(idx).starts_with(ns_prefix.as_bytes()) || mstart < ns_prefix.len() {
+                continue;
+            }
+            self.push_leaf(idx, mstart, infix.len(), 0);
+        }
+    }
+
+    // Pushes the candidates whose description has words starting with the words of the input.
+    // A namespace in the input only restricts the dataset
+    fn push_description_matches(&mut self, input: &str) {
+        let (ns_prefix, words) = match input.rfind(NAMESPACE_SEPARATOR) {
+            Some(pos) => input.split_at(pos + 1),
+            None => ("", input),
+        };
+        for (idx, index) in self.trie.find_description_matches(words) {
+            if self.trie.value(idx).starts_with(ns_prefix.as_bytes()) {
+                self.push_candidate(idx, index, 0, 0, 0);
+            }
+        }
+    }
+
+    fn finish(self, kind: MatchKind) -> Matches {
+        Matches {
+            matches: self
+                .best
+                .into_sorted_vec()
+                .into_iter()
+                .map(|r| r.1)
+                .collect(),
+            total: self.total,
+            kind,
+        }
+    }
+}
+
+/// Finds the first (limit) matches for a given input string, along with the number of all matches
+/// if the input is empty it returns the favorites, if it is not ASCII it returns nothing
+///
+/// An alias with several candidate values yields one match per candidate
+///
+/// Namespace-qualified aliases (ex. `math:alpha`) are only listed if the input contains a namespace,
+/// so typing `math:` restricts the matches to that dataset
+///
+/// Prefix matches come first, followed by aliases containing the input elsewhere (ex. `arrow` finds `rightarrow`)
+/// Favorites are listed first within each group, then an exact match of the whole alias, then the other matches by the ranking.
+/// Ties are kept in tree order, so the order does not depend on the order of the dataset files
+///
+/// If no alias starts with the whole input, the aliases starting with its longest matching part are listed
+/// as prefix matches, and the kind of the result is `MatchKind::Partial` (see `find_corrections`)
+///
+/// All matches are visited, but only the best (limit) are kept
+///
+/// If dataset is given, only its candidates are listed
+pub fn find_matches(
+    trie: &Trie,
+    favorites: &Favorites,
+    input: &str,
+    dataset: Option<usize>,
+    matching: MatchMode,
+    ranking: &Ranking,
+    limit: usize,
+) -> Matches {
+    if input.is_empty() {
+        let mut matches = favorite_matches(trie, favorites, dataset);
+        let total = matches.len();
+        matches.truncate(limit);
+        return Matches {
+            matches,
+            total,
+            kind: MatchKind::Full,
+        };
+    }
+    let mut collector = Collector::new(trie, favorites, input, dataset, ranking, limit);
+    if !input.is_ascii() {
+        return collector.finish(MatchKind::Full);
+    }
+
+    // Prefix matches
+    let mut kind = MatchKind::Full;
+    match matching {
+        MatchMode::Exact => {
+            let (midx, mlen) = trie.find_max_match(input.as_bytes());
+            if mlen < input.len() {
+                kind = MatchKind::Partial;
+            }
+            if midx != 0 {
+                //not root
+                for (idx, _) in trie.iter(midx) {
+                    collector.push_leaf(idx, 0, mlen, 0);
+                }
+            }
+        }
+        MatchMode::Normalized => {
+            let matches = trie.find_normalized_matches(input.as_bytes());
+            if matches.is_empty() {
+                kind = MatchKind::Partial;
+            }
+            for (idx, mlen) in matches {
+                collector.push_leaf(idx, 0, mlen, 0);
+            }
+        }
+    }
+
+    collector.push_infix_matches(input);
+    collector.finish(kind)
+}
+
+/// Lists spelling corrections of an input that no alias starts with, followed by the infix matches as in `find_matches`
+///
+/// Corrections are aliases starting with a misspelling of the input (see `Trie::find_similar`), closest first.
+/// Longer inputs allow more typos, inputs shorter than 3 bytes are not corrected.
+/// With `MatchMode::Normalized`, case and separators are ignored as in `find_matches`.
+/// The kind of the result is `MatchKind::Corrected` if there is any correction, and `MatchKind::Partial` otherwise
+pub fn find_corrections(
+    trie: &Trie,
+    favorites: &Favorites,
+    input: &str,
+    dataset: Option<usize>,
+    matching: MatchMode,
+    ranking: &Ranking,
+    limit: usize,
+) -> Matches {
+    let mut collector = Collector::new(trie, favorites, input, dataset, ranking, limit);
+    if input.is_empty() || !input.is_ascii() {
+        return collector.finish(MatchKind::Partial);
+    }
+    let max_distance = match input.len() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    };
+    if max_distance > 0 {
+        let normalized = matching == MatchMode::Normalized;
+        // Lengths are compared as find_similar does, without separators if normalized
+        let input_len = match normalized {
+            true => normalize_alias(input.as_bytes()).len(),
+            false => input.len(),
+        };
+        for (idx, len, distance) in trie.find_similar(input.as_bytes(), max_distance, normalized) {
+            let prefix = &trie.value(idx)[..len];
+            let prefix_len = match normalized {
+                true => normalize_alias(prefix).len(),
+                false => len,
+            };
+            if prefix_len < input_len {
+                collector.push_leaf(idx, 0, len, distance);
+                continue;
+            }
+            for (leaf, _) in trie.iter(idx) {
+                collector.push_leaf(leaf, 0, len, distance);
+            }
+        }
+    }
+    let kind = if collector.total > 0 {
+        MatchKind::Corrected
+    } else {
+        MatchKind::Partial
+    };
+    collector.push_infix_matches(input);
+    collector.finish(kind)
+}
+
+/// Lists the candidates whose description matches an input that no alias starts with, followed by the infix matches as in `find_matches`
+///
+/// A description matches if it has words starting with every word of the input (see `Trie::find_description_matches`),
+/// so `corner` or `left_corner` find `lcorner` described as "left corner bracket".
+/// The kind of the result is `MatchKind::Description` if any description matches, and `MatchKind::Partial` otherwise
+pub fn find_description_matches(
+    trie: &Trie,
+    favorites: &Favorites,
+    input: &str,
+    dataset: Option<usize>,
+    ranking: &Ranking,
+    limit: usize,
+) -> Matches {
+    let mut collector = Collector::new(trie, favorites, input, dataset, ranking, limit);
+    collector.push_description_matches(input);
+    let kind = if collector.total > 0 {
+        MatchKind::Description
+    } else {
+        MatchKind::Partial
+    };
+    if input.is_ascii() {
+        collector.push_infix_matches(input);
+    }
+    collector.finish(kind)
+}
+
+/// Finds the candidate of an alias at the given index
+pub fn find_candidate(trie: &Trie, alias: &str, index: usize) -> anyhow::Result<Candidate> {
+    trie.find_value(alias)?
+        .get(index)
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("Candidate index {} out of range", index))
+}
+
+/// Finds the first candidate of an alias with the given value, along with its index
+/// If dataset is given, only its candidates are considered
+pub fn find_pinned_candidate(
+    trie: &Trie,
+    alias: &str,
+    value: char,
+    dataset: Option<usize>,
+) -> Option<(usize, Candidate)> {
+    let candidates = trie.find_value(alias).ok()?;
+    candidates
+        .iter()
+        .enumerate()
+        .find(|(_, c)| c.value == value && dataset.is_none_or(|d| d == c.dataset))
+        .map(|(index, c)| (index, *c))
+}
+
+/// Returns the value of a favorite along with its alias and candidate index (empty alias for raw characters)
+/// The candidate is found by its value (see `Favorite`). Returns None if the alias no longer has it
+pub fn resolve_favorite(trie: &Trie, favorite: &Favorite) -> Option<(String, usize, char)> {
+    match favorite {
+        Favorite::Alias { alias, value } => find_pinned_candidate(trie, alias, *value, None)
+            .map(|(index, c)| (alias.clone(), index, c.value)),
+        Favorite::Char { value } => Some((String::new(), 0, *value)),
+    }
+}
+
+/// Lists all favorites as matches, in the order they were added
+/// If dataset is given, only the candidates of that dataset are listed (raw characters are left out)
+pub fn favorite_matches(
+    trie: &Trie,
+    favorites: &Favorites,
+    dataset: Option<usize>,
+) -> Vec<MatchData> {
+    favorites
+        .items
+        .iter()
+        .enumerate()
+        .filter_map(|(pos, favorite)| {
+            let (alias, index, value, description) = match favorite {
+                Favorite::Alias { alias, value } => {
+                    let (index, c) = find_pinned_candidate(trie, alias, *value, dataset)?;
+                    let description = trie.description(&c).map(|d| d.to_owned());
+                    (alias.clone(), index, *value, description)
+                }
+                // Raw characters belong to no dataset
+                Favorite::Char { .. } if dataset.is_some() => return None,
+                Favorite::Char { value } => (String::new(), 0, *value, None),
+            };
+            Some(MatchData {
+                matchstr: alias,
+                matchstart: 0,
+                matchlen: 0,
+                value,
+                index,
+                favorite: Some(pos),
+                score: 0,
+                distance: 0,
+                description,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn trie() -> Trie {
+        let mut trie = Trie::new();
+        let math = trie.add_dataset("math");
+        let misc = trie.add_dataset("misc");
+        for (alias, value, dataset) in [
+            ("pitchfork", '⋔', math),
+            ("pizza", '🍕', misc),
+            ("pi", 'π', misc),
+            ("pi", 'ϖ', math),
+            ("epsilon", 'ε', misc),
+        ] {
+            trie.append_leaf(alias.to_string(), value, dataset).unwrap();
+        }
+        trie.build_infix_index();
+        trie
+    }
+
+    fn values(trie: &Trie, ranking: &Ranking, input: &str, limit: usize) -> Vec<char> {
+        let matches = find_matches(
+            trie,
+            &Favorites::default(),
+            input,
+            None,
+            MatchMode::Exact,
+            ranking,
+            limit,
+        );
+        matches.matches.iter().map(|m| m.value).collect()
+    }
+
+    fn ranking(order: MatchOrder, trie: &Trie) -> Ranking<'static> {
+        static USAGE: Usage = Usage {
+            chars: BTreeMap::new(),
+        };
+        Ranking::new(order, trie, &[], &USAGE)
+    }
+
+    #[test]
+    fn namespaces() {
+        let mut trie = Trie::new();
+        let math = trie.add_dataset("math");
+        let greek = trie.add_dataset("greek");
+        for (namespace, alias, value, dataset) in [
+            (Some("math"), "alpha", '𝛼', math),
+            (Some("math"), "in", '∈', math),
+            (None, "alpha", 'α', greek),
+        ] {
+            let errors = crate::dataset::append_entry(
+                &mut trie,
+                namespace,
+                alias.to_string(),
+                value,
+                None,
+                dataset,
+            );
+            assert!(errors.is_empty());
+        }
+        trie.build_infix_index();
+        let ranking = ranking(MatchOrder::Lexicographic, &trie);
+        // Qualified aliases are only listed for a namespace, which restricts the matches to its dataset
+        assert_eq!(values(&trie, &ranking, "al", 10), ['𝛼', 'α']);
+        assert_eq!(values(&trie, &ranking, "math:", 10), ['𝛼', '∈']);
+        assert_eq!(values(&trie, &ranking, "math:al", 10), ['𝛼']);
+        assert_eq!(values(&trie, &ranking, "pha", 10), ['𝛼', 'α']);
+        assert_eq!(values(&trie, &ranking, "greek:", 10), []);
+    }
+
+    #[test]
+    fn one_match_per_candidate() {
+        let trie = trie();
+        let matches = find_matches(
+            &trie,
+            &Favorites::default(),
+            "pi",
+            None,
+            MatchMode::Exact,
+            &ranking(MatchOrder::Lexicographic, &trie),
+            10,
+        );
+        let pi: Vec<(&str, usize, char)> = matches
+            .matches
+            .iter()
+            .filter(|m| m.matchstr == "pi")
+            .map(|m| (m.matchstr.as_str(), m.index, m.value))
+            .collect();
+        assert_eq!(pi, [("pi", 0, 'π'), ("pi", 1, 'ϖ')]);
+        assert_eq!(find_candidate(&trie, "pi", 1).unwrap().value, 'ϖ');
+        assert!(find_candidate(&trie, "pi", 2).is_err());
+        // Only the candidates of the dataset are listed
+        let math = find_matches(
+            &trie,
+            &Favorites::default(),
+            "pi",
+            Some(0),
+            MatchMode::Exact,
+            &ranking(MatchOrder::Lexicographic, &trie),
+            10,
+        );
+        let values: Vec<char> = math.matches.iter().map(|m| m.value).collect();
+        assert_eq!(values, ['ϖ', '⋔']);
+    }
+
+    #[test]
+    fn orders() {
+        let trie = trie();
+        let lexicographic = ranking(MatchOrder::Lexicographic, &trie);
+        assert_eq!(
+            values(&trie, &lexicographic, "pi", 10),
+            ['π', 'ϖ', '⋔', '🍕']
+        );
+        let shortest = ranking(MatchOrder::Shortest, &trie);
+        assert_eq!(values(&trie, &shortest, "pi", 10), ['π', 'ϖ', '🍕', '⋔']);
+        // Exact matches come first whatever the order
+        let datasets = ranking(MatchOrder::Dataset, &trie);
+        assert_eq!(values(&trie, &datasets, "pi", 10), ['ϖ', 'π', '⋔', '🍕']);
+        let usage = Usage::default();
+        let datasets = Ranking::new(MatchOrder::Dataset, &trie, &["misc".to_string()], &usage);
+        assert_eq!(values(&trie, &datasets, "pi", 10), ['π', 'ϖ', '🍕', '⋔']);
+        // Infix matches still come last
+        assert_eq!(
+            values(&trie, &datasets, "p", 10),
+            ['π', '🍕', 'ϖ', '⋔', 'ε']
+        );
+    }
+
+    #[test]
+    fn frecency_order() {
+        let trie = trie();
+        let mut usage = Usage::default();
+        usage.record('🍕', 0);
+        usage.record('🍕', 0);
+        usage.record('⋔', 0);
+        // Recent use outweighs a larger count
+        usage.record('ϖ', 0);
+        usage.record('ϖ', 0);
+        usage.record('ϖ', 0);
+        let now = 100 * 24 * 60 * 60;
+        usage.record('⋔', now);
+        let frecency = Ranking {
+            now,
+            ..Ranking::new(MatchOrder::Frecency, &trie, &[], &usage)
+        };
+        assert_eq!(
+            values(&trie, &frecency, "p", 10),
+            ['⋔', 'ϖ', '🍕', 'π', 'ε']
+        );
+    }
+
+    #[test]
+    fn score_order() {
+        let mut trie = Trie::new();
+        let dataset = trie.add_dataset("math");
+        for (alias, value) in [
+            ("infinity", '∞'),
+            ("int", '∫'),
+            ("in_set", '⋳'),
+            ("in", '∈'),
+            ("notin", '∉'),
+            ("mint", 'm'),
+        ] {
+            trie.append_leaf(alias.to_string(), value, dataset).unwrap();
+        }
+        trie.build_infix_index();
+        let score = ranking(MatchOrder::Score, &trie);
+        assert_eq!(
+            values(&trie, &score, "in", 10),
+            ['∈', '⋳', '∫', '∞', '∉', 'm']
+        );
+        assert!(match_score("in", 0, 2, 0) > match_score("in_set", 0, 2, 0));
+        assert!(match_score("in_set", 0, 2, 0) > match_score("int", 0, 2, 0));
+        assert!(match_score("int", 0, 2, 0) > match_score("int", 0, 2, 1));
+        assert!(match_score("int", 0, 2, 1) > match_score("intx", 0, 2, 0));
+        assert!(match_score("a_in", 2, 2, 0) > match_score("ain", 1, 2, 0));
+    }
+
+    #[test]
+    fn normalized_matching() {
+        let mut trie = Trie::new();
+        let dataset = trie.add_dataset("test");
+        for (alias, value) in [("Alpha", 'Α'), ("alpha", 'α'), ("left_arrow", '←')] {
+            trie.append_leaf(alias.to_string(), value, dataset).unwrap();
+        }
+        trie.build_infix_index();
+        let favorites = Favorites::default();
+        let ranking = ranking(MatchOrder::Score, &trie);
+        let find = |input: &str, matching| {
+            let matches = find_matches(&trie, &favorites, input, None, matching, &ranking, 10);
+            let values: Vec<(char, usize)> = matches
+                .matches
+                .iter()
+                .map(|m| (m.value, m.matchlen))
+                .collect();
+            (matches.kind, values)
+        };
+        // Case and separators are ignored, and the alias of the same case comes first
+        assert_eq!(
+            find("ALP", MatchMode::Normalized),
+            (MatchKind::Full, vec![('Α', 3), ('α', 3)])
+        );
+        assert_eq!(
+            find("leftArrow", MatchMode::Normalized),
+            (MatchKind::Full, vec![('←', 10)])
+        );
+        assert_eq!(find("leftArrow", MatchMode::Exact).0, MatchKind::Partial);
+
+        // Corrections follow the match mode
+        let corrected = |input: &str, matching| -> Vec<char> {
+            find_corrections(&trie, &favorites, input, None, matching, &ranking, 10)
+                .matches
+                .iter()
+                .map(|m| m.value)
+                .collect()
+        };
+        assert_eq!(corrected("Alpz", MatchMode::Normalized), ['Α', 'α']);
+        assert_eq!(corrected("Alpz", MatchMode::Exact), ['Α']);
+        assert_eq!(corrected("Left-arorw", MatchMode::Normalized), ['←']);
+        assert!(corrected("Left-arorw", MatchMode::Exact).is_empty());
+    }
+
+    #[test]
+    fn corrections() {
+        let trie = trie();
+        let favorites = Favorites::default();
+        let ranking = ranking(MatchOrder::Score, &trie);
+        let full = find_matches(
+            &trie,
+            &favorites,
+            "piz",
+            None,
+            MatchMode::Exact,
+            &ranking,
+            10,
+        );
+        assert_eq!(full.kind, MatchKind::Full);
+        // The partial match lists everything under "piz"
+        let partial = find_matches(
+            &trie,
+            &favorites,
+            "pizx",
+            None,
+            MatchMode::Exact,
+            &ranking,
+            10,
+        );
+        assert_eq!(partial.kind, MatchKind::Partial);
+        assert_eq!(partial.total, 1);
+
+        let corrected = find_corrections(
+            &trie,
+            &favorites,
+            "pizx",
+            None,
+            MatchMode::Exact,
+            &ranking,
+            10,
+        );
+        assert_eq!(corrected.kind, MatchKind::Corrected);
+        let matches: Vec<(char, usize, usize)> = corrected
+            .matches
+            .iter()
+            .map(|m| (m.value, m.matchlen, m.distance))
+            .collect();
+        assert_eq!(matches, [('🍕', 4, 1)]);
+        // Transposed and too short inputs
+        let transposed = find_corrections(
+            &trie,
+            &favorites,
+            "ipzza",
+            None,
+            MatchMode::Exact,
+            &ranking,
+            10,
+        );
+        assert_eq!(transposed.matches[0].value, '🍕');
+        let short = find_corrections(
+            &trie,
+            &favorites,
+            "px",
+            None,
+            MatchMode::Exact,
+            &ranking,
+            10,
+        );
+        assert_eq!(short.kind, MatchKind::Partial);
+        assert!(short.matches.is_empty());
+    }
+
+    #[test]
+    fn description_matches() {
+        let mut trie = trie();
+        let candidate = Candidate {
+            value: '🍰',
+            dataset: 1,
+            description: Some(trie.add_description("Slice of cake")),
+        };
+        trie.append_candidate("shortcake".to_string(), candidate)
+            .unwrap();
+        trie.build_infix_index();
+        let favorites = Favorites::default();
+        let ranking = ranking(MatchOrder::Score, &trie);
+
+        let described = find_description_matches(&trie, &favorites, "slice", None, &ranking, 10);
+        assert_eq!(described.kind, MatchKind::Description);
+        let matches: Vec<(char, Option<&str>)> = described
+            .matches
+            .iter()
+            .map(|m| (m.value, m.description.as_deref()))
+            .collect();
+        assert_eq!(matches, [('🍰', Some("Slice of cake"))]);
+        // Also an infix match, listed once
+        let cake = find_description_matches(&trie, &favorites, "cake", None, &ranking, 10);
+        assert_eq!((cake.kind, cake.total), (MatchKind::Description, 1));
+        let none = find_description_matches(&trie, &favorites, "pie", None, &ranking, 10);
+        assert_eq!(none.kind, MatchKind::Partial);
+        assert!(none.matches.is_empty());
+    }
+
+    #[test]
+    fn infix_matches_follow_prefix_matches() {
+        let mut trie = Trie::new();
+        let dataset = trie.add_dataset("test");
+        for (alias, value) in [
+            ("rightarrow", '→'),
+            ("arrowhead", '⌃'),
+            ("narrow", 'n'),
+            ("arrow_arrow", '⇉'),
+            ("arrow", '↑'),
+        ] {
+            trie.append_leaf(alias.to_string(), value, dataset).unwrap();
+        }
+        trie.build_infix_index();
+        let ranking = ranking(MatchOrder::Lexicographic, &trie);
+        let matches = find_matches(
+            &trie,
+            &Favorites::default(),
+            "arrow",
+            None,
+            MatchMode::Exact,
+            &ranking,
+            10,
+        );
+        let found: Vec<(&str, usize)> = matches
+            .matches
+            .iter()
+            .map(|m| (m.matchstr.as_str(), m.matchstart))
+            .collect();
+        // The exact match, the other prefix matches, then the infix matches.
+        // "arrow_arrow" is a prefix match only
+        assert_eq!(
+            found,
+            [
+                ("arrow", 0),
+                ("arrow_arrow", 0),
+                ("arrowhead", 0),
+                ("narrow", 1),
+                ("rightarrow", 5)
+            ]
+        );
+        assert_eq!(matches.total, 5);
+    }
+
+    #[test]
+    fn favorites_are_pinned_by_value() {
+        let favorites = Favorites {
+            items: vec![
+                Favorite::Alias {
+                    alias: "pi".to_string(),
+                    value: 'ϖ',
+                },
+                Favorite::Char { value: '★' },
+            ],
+        };
+        let listed = |trie: &Trie, dataset| -> Vec<(String, usize, char)> {
+            favorite_matches(trie, &favorites, dataset)
+                .into_iter()
+                .map(|m| (m.matchstr, m.index, m.value))
+                .collect()
+        };
+        let trie = trie();
+        assert_eq!(
+            listed(&trie, None),
+            [("pi".to_string(), 1, 'ϖ'), (String::new(), 0, '★')]
+        );
+        assert_eq!(listed(&trie, Some(0)), [("pi".to_string(), 1, 'ϖ')]);
+        assert!(listed(&trie, Some(1)).is_empty());
+        let ranking = ranking(MatchOrder::Lexicographic, &trie);
+        let matches = find_matches(
+            &trie,
+            &favorites,
+            "pi",
+            None,
+            MatchMode::Exact,
+            &ranking,
+            10,
+        );
+        assert_eq!(matches.matches[0].value, 'ϖ');
+        assert_eq!(matches.matches[0].favorite, Some(0));
+
+        // Without the dataset listed first, the candidate moves but stays pinned
+        let mut trie = Trie::new();
+        let math = trie.add_dataset("math");
+        trie.append_leaf("pi".to_string(), 'ϖ', math).unwrap();
+        assert_eq!(listed(&trie, None)[0], ("pi".to_string(), 0, 'ϖ'));
+        // A value the alias no longer has is left out
+        let mut trie = Trie::new();
+        let misc = trie.add_dataset("misc");
+        trie.append_leaf("pi".to_string(), 'π', misc).unwrap();
+        assert_eq!(listed(&trie, None), [(String::new(), 0, '★')]);
+    }
+
+    #[test]
+    fn pages_are_sliced_from_the_cache() {
+        let trie = trie();
+        let ranking = ranking(MatchOrder::Shortest, &trie);
+        let query = ("p".to_string(), None, MatchMode::Exact);
+        let search = |limit| {
+            let result = find_matches(
+                &trie,
+                &Favorites::default(),
+                "p",
+                None,
+                MatchMode::Exact,
+                &ranking,
+                limit,
+            );
+            MatchCache {
+                query: query.clone(),
+                limit,
+                result,
+            }
+        };
+        let all = values(&trie, &ranking, "p", usize::MAX);
+        // List the matches 2 by 2, searching only when needed
+        let mut cache: Option<MatchCache> = None;
+        let (mut listed, mut searches) = (Vec::new(), Vec::new());
+        for offset in (0..all.len()).step_by(2) {
+            if let Some(limit) = MatchCache::search_limit(cache.as_ref(), &query, offset, 2) {
+                searches.push(limit);
+                cache = Some(search(limit));
+            }
+            let result = &cache.as_ref().unwrap().result;
+            listed.extend(result.matches.iter().skip(offset).take(2).map(|m| m.value));
+            assert_eq!(result.total, all.len());
+        }
+        assert_eq!(listed, all);
+        assert_eq!(searches, [2, 4, 8]);
+        // Another query, or the first page, is searched again
+        let other = ("pi".to_string(), None, MatchMode::Exact);
+        assert_eq!(
+            MatchCache::search_limit(cache.as_ref(), &other, 2, 2),
+            Some(4)
+        );
+        assert_eq!(
+            MatchCache::search_limit(cache.as_ref(), &query, 0, 2),
+            Some(2)
+        );
+        // Every match is cached
+        assert_eq!(
+            MatchCache::search_limit(cache.as_ref(), &query, 4, 10),
+            None
+        );
+    }
+
+    #[test]
+    fn limit_keeps_the_best_matches() {
+        let trie = trie();
+        for order in [MatchOrder::Lexicographic, MatchOrder::Shortest] {
+            let ranking = ranking(order, &trie);
+            let all = values(&trie, &ranking, "p", usize::MAX);
+            for limit in 0..=all.len() {
+                assert_eq!(values(&trie, &ranking, "p", limit), all[..limit]);
+            }
+        }
+        let total = find_matches(
+            &trie,
+            &Favorites::default(),
+            "p",
+            None,
+            MatchMode::Exact,
+            &ranking(MatchOrder::Shortest, &trie),
+            1,
+        )
+        .total;
+        assert_eq!(total, 5);
+    }
+}
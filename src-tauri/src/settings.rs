@@ -0,0 +1,348 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use tauri_plugin_global_shortcut::Shortcut;
+
+/// Version of the settings schema. Bump it and add a step to `migrate` when the schema changes
+pub const SETTINGS_VERSION: u32 = 3;
+/// Allowed range of the number of results per page
+pub const MAX_RESULTS_RANGE: std::ops::RangeInclusive<usize> = 1..=50;
+
+/// How the input is compared against aliases
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Case-sensitive prefix matching
+    Exact,
+    /// Ignores case and separators (`_`, `-`), case only breaks ties
+    Normalized,
+}
+
+/// How matches are ordered within the prefix and infix matches (favorites always come first)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchOrder {
+    /// Best match first (see `matching::match_score`)
+    Score,
+    /// Alphabetical order of the aliases
+    Lexicographic,
+    /// Shorter aliases first (`pi` before `pitchfork`)
+    Shortest,
+    /// Candidates of datasets earlier in `dataset_priority` first
+    Dataset,
+    /// Frequently and recently inserted characters first
+    Frecency,
+}
+
+/// How the selected character is sent to the focused application
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InsertMethod {
+    /// Types the character as text
+    Text,
+    /// Presses the character as a unicode key. Some applications ignore text input but accept this
+    Key,
+}
+
+/// What happens when a hotkey is pressed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum HotkeyAction {
+    /// Opens the popup. `dataset` restricts the matches to a dataset, `matching` overrides the matching mode
+    Popup {
+        #[serde(default)]
+        dataset: Option<String>,
+        #[serde(default)]
+        matching: Option<MatchMode>,
+    },
+    /// Inserts the most recently inserted character again
+    Repeat,
+    /// Inserts a candidate of an alias
+    Alias {
+        alias: String,
+        #[serde(default)]
+        index: usize,
+    },
+}
+
+/// A global hotkey (ex. `alt+shift+u`) and its action
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HotkeyBinding {
+    pub hotkey: String,
+    pub action: HotkeyAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+/// Settings of the app, persisted as `settings.json` in the app data directory
+///
+/// Missing fields take their default values
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AppSettings {
+    pub version: u32,
+    pub hotkeys: Vec<HotkeyBinding>,
+    pub matching: MatchMode,
+    pub order: MatchOrder,
+    /// Names of the datasets in order of priority for `MatchOrder::Dataset`. Unlisted datasets come last
+    pub dataset_priority: Vec<String>,
+    /// Whether spelling corrections are listed when no alias starts with the input
+    pub suggest_corrections: bool,
+    /// Number of results per page
+    pub max_results: usize,
+    pub insert_method: InsertMethod,
+    pub theme: Theme,
+    /// Names of the datasets that are not loaded (all datasets are enabled by default)
+    pub disabled_datasets: Vec<String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            version: SETTINGS_VERSION,
+            hotkeys: vec![HotkeyBinding {
+                hotkey: "alt+shift+u".to_string(),
+                action: HotkeyAction::Popup {
+                    dataset: None,
+                    matching: None,
+                },
+            }],
+            matching: MatchMode::Exact,
+            order: MatchOrder::Score,
+            dataset_priority: Vec::new(),
+            suggest_corrections: true,
+            max_results: 5,
+            insert_method: InsertMethod::Text,
+            theme: Theme::Light,
+            disabled_datasets: Vec::new(),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Reads the settings from a JSON file. A missing file is treated as the default settings
+    ///
+    /// Files of an older schema are migrated and written back
+    /// Returns Err if the file is malformed, of a newer schema, or has invalid values
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let value: Value = match File::open(path) {
+            Ok(file) => serde_json::from_reader(io::BufReader::new(file))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(AppSettings::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let (value, migrated) = migrate(value)?;
+        let settings: AppSettings = serde_json::from_value(value)?;
+        let errors = settings.validate();
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!(errors.join("\n")));
+        }
+        if migrated {
+            log::info!("Migrated settings to version {}", SETTINGS_VERSION);
+            settings.save(path)?;
+        }
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns a message for each invalid value (empty if the settings are valid)
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.version != SETTINGS_VERSION {
+            errors.push(format!(
+                "Unsupported settings version {} (expected {})",
+                self.version, SETTINGS_VERSION
+            ));
+        }
+        let mut shortcuts: Vec<Shortcut> = Vec::new();
+        for binding in &self.hotkeys {
+            match Shortcut::from_str(&binding.hotkey) {
+                Ok(shortcut) if shortcuts.contains(&shortcut) => errors.push(format!(
+                    "Hotkey {:?} is bound more than once",
+                    binding.hotkey
+                )),
+                Ok(shortcut) => shortcuts.push(shortcut),
+                Err(e) => errors.push(format!("Invalid hotkey {:?}: {}", binding.hotkey, e)),
+            }
+            if let HotkeyAction::Alias { alias, .. } = &binding.action {
+                if alias.trim().is_empty() {
+                    errors.push(format!(
+                        "Hotkey {:?} has no alias to insert",
+                        binding.hotkey
+                    ));
+                }
+            }
+        }
+        if !MAX_RESULTS_RANGE.contains(&self.max_results) {
+            errors.push(format!(
+                "Results per page should be between {} and {}",
+                MAX_RESULTS_RANGE.start(),
+                MAX_RESULTS_RANGE.end()
+            ));
+        }
+        errors
+    }
+}
+
+/// Upgrades a settings file to the current schema, one version at a time
+/// Returns the upgraded value and whether it was changed
+fn migrate(mut value: Value) -> anyhow::Result<(Value, bool)> {
+    let obj = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("Settings file is not a JSON object"))?;
+    // Version 1 had no version field and stored every value as a string
+    let version = match obj.get("version") {
+        None => 1,
+        Some(v) => v
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Invalid settings version: {}", v))?
+            as u32,
+    };
+    if version > SETTINGS_VERSION {
+        return Err(anyhow::anyhow!(
+            "Settings file version {} is newer than supported ({})",
+            version,
+            SETTINGS_VERSION
+        ));
+    }
+
+    if version < 2 {
+        // "max_results": "5" -> "max_results": 5
+        if let Some(Value::String(s)) = obj.get("max_results") {
+            let cnt: usize = s
+                .trim()
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid maximum number of results: {:?}", s))?;
+            obj.insert("max_results".to_string(), cnt.into());
+        }
+    }
+
+    if version < 3 {
+        // "hotkey": "alt+shift+u" -> "hotkeys": [{ "hotkey": "alt+shift+u", "action": { "kind": "popup" } }]
+        if let Some(hotkey) = obj.remove("hotkey") {
+            let binding = serde_json::json!({ "hotkey": hotkey, "action": { "kind": "popup" } });
+            obj.insert("hotkeys".to_string(), Value::Array(vec![binding]));
+        }
+    }
+
+    obj.insert("version".to_string(), SETTINGS_VERSION.into());
+    Ok((value, version < SETTINGS_VERSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Writes a settings file under the temporary directory
+    fn settings_file(name: &str, text: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "unialias-settings-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        // Every value was a string, and there was no version
+        let (value, migrated) = migrate(json!({ "max_results": " 7 ", "theme": "dark" })).unwrap();
+        assert!(migrated);
+        let settings: AppSettings = serde_json::from_value(value).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.max_results, 7);
+        assert_eq!(settings.theme, Theme::Dark);
+        assert!(settings.validate().is_empty());
+
+        assert!(migrate(json!({ "max_results": "many" })).is_err());
+    }
+
+    #[test]
+    fn migrates_a_single_hotkey() {
+        let (value, migrated) = migrate(json!({ "version": 2, "hotkey": "alt+shift+x" })).unwrap();
+        assert!(migrated);
+        let settings: AppSettings = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            settings.hotkeys,
+            [HotkeyBinding {
+                hotkey: "alt+shift+x".to_string(),
+                action: HotkeyAction::Popup {
+                    dataset: None,
+                    matching: None,
+                },
+            }]
+        );
+        // The current version is left as is
+        let current = serde_json::to_value(AppSettings::default()).unwrap();
+        assert_eq!(migrate(current.clone()).unwrap(), (current, false));
+    }
+
+    #[test]
+    fn rejects_newer_and_malformed_files() {
+        let newer = json!({ "version": SETTINGS_VERSION + 1 });
+        assert!(migrate(newer).is_err());
+        assert!(migrate(json!({ "version": "3" })).is_err());
+        assert!(migrate(json!([])).is_err());
+
+        let path = settings_file("malformed", "{ \"version\": 3,");
+        assert!(AppSettings::load(&path).is_err());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn validates_values() {
+        assert!(AppSettings::default().validate().is_empty());
+        for max_results in [0, MAX_RESULTS_RANGE.end() + 1] {
+            let settings = AppSettings {
+                max_results,
+                ..AppSettings::default()
+            };
+            assert_eq!(settings.validate().len(), 1);
+        }
+        let mut settings = AppSettings::default();
+        settings.hotkeys.push(settings.hotkeys[0].clone());
+        settings.hotkeys.push(HotkeyBinding {
+            hotkey: "alt+shift+r".to_string(),
+            action: HotkeyAction::Alias {
+                alias: " ".to_string(),
+                index: 0,
+            },
+        });
+        assert_eq!(settings.validate().len(), 2);
+    }
+
+    #[test]
+    fn load_migrates_and_saves() {
+        let path = settings_file("migrated", r#"{ "version": 2, "max_results": 9 }"#);
+        let settings = AppSettings::load(&path).unwrap();
+        assert_eq!(settings.max_results, 9);
+        // Written back in the current version
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], SETTINGS_VERSION);
+        assert_eq!(AppSettings::load(&path).unwrap(), settings);
+        let _ = fs::remove_file(&path);
+
+        // Invalid values are not loaded
+        let path = settings_file("invalid", r#"{ "version": 3, "max_results": 0 }"#);
+        assert!(AppSettings::load(&path).is_err());
+        let _ = fs::remove_file(&path);
+        // A missing file is the default settings
+        assert_eq!(AppSettings::load(&path).unwrap(), AppSettings::default());
+    }
+}
@@ -1,310 +1,1724 @@
 use anyhow::{Ok, Result};
 use core::fmt;
+use memmap2::Mmap;
 use std::cmp::min;
 use std::fmt::Display;
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+use std::ops::Deref;
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, TryFromBytes};
 
-#[derive(Debug)]
-pub enum TrieNodeContent {
-    Internal { children: Vec<usize> }, // Using i32 for child indices
-    Leaf { data: char },
+/// A value an alias may be replaced with
+#[derive(Debug, Clone, Copy, PartialEq, TryFromBytes, IntoBytes, KnownLayout, Immutable)]
+#[repr(C)]
+pub struct Candidate {
+    pub value: char,
+    pub description: Option<NonZeroU32>, //see `Trie::add_description`
+    pub dataset: usize,                  //index into Trie::datasets
 }
 
-#[derive(Debug)]
+/// A node of the trie. Its label is the part of the alias between the parent and this node
+///
+/// Every appended alias is stored whole in `Trie::keys`, and labels are slices of it.
+/// So the path from the root to a node is `keys[label_end - depth..label_end]`, without walking up the tree
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, KnownLayout, Immutable)]
+#[repr(C)]
 pub struct TrieNode {
-    pub value: Box<[u8]>, //ASCII string
-    pub parent: usize,
-    pub content: TrieNodeContent,
+    label_end: u32, //end of the label in Trie::keys
+    label_len: u32,
+    depth: u32, //length of the path from the root
+    parent: u32,
+    terminal: u32, //index into Trie::candidates if an alias ends at this node, NOT_TERMINAL otherwise
 }
 
+const NOT_TERMINAL: u32 = u32::MAX;
+
 impl TrieNode {
-    // Adds child if the node is internal
-    fn try_add_child(&mut self, child_idx: usize) -> bool {
-        if let TrieNodeContent::Internal { children } = &mut self.content {
-            children.push(child_idx);
-            return true;
-        }
-        false
+    fn terminal(&self) -> Option<usize> {
+        (self.terminal != NOT_TERMINAL).then_some(self.terminal as usize)
     }
+}
 
-    fn try_add_child_front(&mut self, child_idx: usize) -> bool {
-        if let TrieNodeContent::Internal { children } = &mut self.content {
-            children.insert(0, child_idx);
-            return true;
-        }
-        false
-    }
+// A child of a node: the first byte of its label and its index
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, KnownLayout, Immutable)]
+#[repr(C)]
+struct Edge {
+    byte: u8,
+    _padding: [u8; 3],
+    node: u32,
+}
 
-    // Removes child if the node is internal
-    fn try_remove_child(&mut self, child_idx: usize) -> bool {
-        if let TrieNodeContent::Internal { children } = &mut self.content {
-            if let Some(pos) = children.iter().position(|&x| x == child_idx) {
-                children.remove(pos);
-                return true;
-            }
+impl Edge {
+    fn new(byte: u8, node: u32) -> Self {
+        Edge {
+            byte,
+            _padding: [0; 3],
+            node,
         }
-        false
-    }
-
-    pub fn value_str(&self) -> &str {
-        std::str::from_utf8(&self.value).unwrap()
     }
 }
 
 //root node index = 0
+//the arrays are either built in memory or borrowed from the dataset cache (see `Trie::from_mapped`).
+//A borrowed array is copied the first time the trie is modified
 pub struct Trie {
-    pub nodes: Vec<TrieNode>,
+    nodes: Array<TrieNode>,
+    children: Lists<Edge>, //children of each node, sorted by the first byte of their labels
+    keys: Array<u8>,       //appended aliases, which the labels point into
+    candidates: Lists<Candidate>, //candidates of each terminal node, in the order they were appended
+    pub datasets: Vec<String>,    //names of the datasets the candidates came from
+    infix_index: Array<[u32; 2]>, //(terminal idx, offset), sorted by the suffix of the alias starting at offset
+    descriptions: Texts,          //descriptions of the candidates
+    //(terminal idx, candidate index, offset), sorted by the case-folded description of the candidate starting at offset (a word start)
+    word_index: Array<[u32; 3]>,
+    _map: Option<Mmap>, //the cache the arrays are borrowed from, kept mapped as long as the trie
 }
 
 impl Trie {
     pub fn new() -> Self {
         // Initialize a new Trie with an empty root node
         let root = TrieNode {
-            value: Box::new([]),
+            label_end: 0,
+            label_len: 0,
+            depth: 0,
             parent: 0,
-            content: TrieNodeContent::Internal {
-                children: Vec::new(),
-            },
+            terminal: NOT_TERMINAL,
         };
-        Trie { nodes: vec![root] }
+        Trie {
+            nodes: Array::Owned(vec![root]),
+            children: Lists::Owned(vec![Vec::new()]),
+            keys: Array::Owned(Vec::new()),
+            candidates: Lists::Owned(Vec::new()),
+            datasets: Vec::new(),
+            infix_index: Array::Owned(Vec::new()),
+            descriptions: Texts::Owned(Vec::new()),
+            word_index: Array::Owned(Vec::new()),
+            _map: None,
+        }
     }
 
-    /// Finds the index of the best match (1. largest match 2. higher in the tree) and return the length of the match
+    /// Writes the arrays of the trie (all but the datasets), to be borrowed by `from_mapped`
+    pub fn write_arrays(&self, out: &mut impl Write) -> io::Result<()> {
+        let (child_ends, children) = self.children.flatten();
+        let (candidate_ends, candidates) = self.candidates.flatten();
+        let mut description_ends = Vec::with_capacity(self.descriptions.len());
+        let mut description_text = String::new();
+        for i in 0..self.descriptions.len() {
+            description_text.push_str(self.descriptions.get(i).unwrap());
+            description_ends.push(description_text.len() as u32);
+        }
+        write_array(out, &self.nodes)?;
+        write_array(out, &child_ends)?;
+        write_array(out, &children)?;
+        write_array(out, &self.keys)?;
+        write_array(out, &candidate_ends)?;
+        write_array(out, &candidates)?;
+        write_array(out, &description_ends)?;
+        write_array(out, description_text.as_bytes())?;
+        write_array(out, &self.infix_index)?;
+        write_array(out, &self.word_index)
+    }
+
+    /// Borrows a trie from the arrays written by `write_arrays`, starting at `offset` of the map (a multiple of 8)
     ///
-    /// The best match may be an internal node or a leaf
+    /// Nothing is copied. Returns Err if an array is truncated or has invalid values, but the structure
+    /// of the trie is not checked: call `validate` before using a trie read from an untrusted file
+    pub fn from_mapped(map: Mmap, offset: usize, datasets: Vec<String>) -> Result<Trie> {
+        // SAFETY: the slices point into the map, whose memory stays in place until it is dropped along
+        // with the trie. They are only handed out with the lifetime of the trie
+        let bytes: &'static [u8] = unsafe { &*(&map[..] as *const [u8]) };
+        if !offset.is_multiple_of(8) {
+            return Err(anyhow::anyhow!("Mapped trie is misaligned"));
+        }
+        let mut arrays = MappedArrays { bytes, pos: offset };
+        let nodes = Array::Mapped(arrays.next()?);
+        let children = Lists::mapped(arrays.next()?, arrays.next()?)?;
+        let keys = Array::Mapped(arrays.next()?);
+        let candidates = Lists::mapped(arrays.next()?, arrays.next()?)?;
+        let descriptions = Texts::mapped(arrays.next()?, arrays.next()?)?;
+        Ok(Trie {
+            nodes,
+            children,
+            keys,
+            candidates,
+            datasets,
+            infix_index: Array::Mapped(arrays.next()?),
+            descriptions,
+            word_index: Array::Mapped(arrays.next()?),
+            _map: Some(map),
+        })
+    }
+
+    /// Registers a dataset and returns its index, to be passed to `append_leaf`
+    pub fn add_dataset(&mut self, name: &str) -> usize {
+        self.datasets.push(name.to_string());
+        self.datasets.len() - 1
+    }
+
+    /// Stores a description and returns its id, to be passed to `append_candidate`
+    pub fn add_description(&mut self, text: &str) -> NonZeroU32 {
+        let descriptions = self.descriptions.to_mut();
+        descriptions.push(text.to_string());
+        NonZeroU32::new(descriptions.len() as u32).unwrap()
+    }
+
+    /// The description of a candidate, if it has one
+    pub fn description(&self, candidate: &Candidate) -> Option<&str> {
+        let id = candidate.description?;
+        self.descriptions.get(id.get() as usize - 1)
+    }
+
+    /// The path from the root to a node (the whole alias, if the node is terminal)
+    pub fn value(&self, idx: usize) -> &[u8] {
+        let node = &self.nodes[idx];
+        let end = node.label_end as usize;
+        &self.keys[end - node.depth as usize..end]
+    }
+
+    pub fn value_str(&self, idx: usize) -> &str {
+        std::str::from_utf8(self.value(idx)).unwrap()
+    }
+
+    // The part of the path between the parent and the node
+    fn label(&self, idx: usize) -> &[u8] {
+        let node = &self.nodes[idx];
+        let end = node.label_end as usize;
+        &self.keys[end - node.label_len as usize..end]
+    }
+
+    /// Returns the candidates of the alias ending at the node, or None if no alias ends there
+    pub fn candidates(&self, idx: usize) -> Option<&[Candidate]> {
+        self.nodes[idx].terminal().map(|t| self.candidates.get(t))
+    }
+
+    // Finds the child whose label starts with the byte
+    fn find_child(&self, idx: usize, byte: u8) -> Option<usize> {
+        let children = self.children.get(idx);
+        children
+            .binary_search_by_key(&byte, |e| e.byte)
+            .ok()
+            .map(|pos| children[pos].node as usize)
+    }
+
+    /// Finds the index of the best match (the deepest node whose path shares the most bytes with input) and return the length of the match
+    ///
+    /// The match may end in the middle of the label of the node
     ///
     /// Return type = (idx : usize, len : usize)
     pub fn find_max_match(&self, input: &[u8]) -> (usize, usize) {
-        if input.len() == 0 {
-            return (0, 0);
-        } //we can assume length >= 1
-
         let mut ndidx: usize = 0; //index for node traversal
-        let mut chidx: usize = 0; //index to keep track of the character needed for matching
-                                  //chidx is always set to the index value that should be checked next
-
-        'outer: loop {
-            let cur_node = &self.nodes[ndidx];
-            let nd_chars = &cur_node.value;
+        let mut chidx: usize = 0; //length of the input matched so far
 
-            // we try to make chidx large as possible (within the bounds)
-            let min_len: usize = min(nd_chars.len(), input.len());
-            while chidx < min_len && input[chidx] == nd_chars[chidx] {
-                chidx += 1
+        while chidx < input.len() {
+            let Some(child) = self.find_child(ndidx, input[chidx]) else {
+                break;
+            };
+            let label = self.label(child);
+            let rest = &input[chidx..];
+            let max_len = min(label.len(), rest.len());
+            let mut common = 1; //the first byte matched when finding the child
+            while common < max_len && label[common] == rest[common] {
+                common += 1;
             }
-
-            if chidx == input.len() || chidx < nd_chars.len() {
-                //this is the best match, case 1: the match length cannot get larger, case 2: we don't need to care children
+            ndidx = child;
+            chidx += common;
+            if common < label.len() {
+                //the match ends inside the label
                 break;
             }
-            //we can do cur_node[chidx] now
+        }
+        (ndidx, chidx)
+    }
+
+    /// Searches for the alias "input", and returns its candidate values
+    ///
+    /// Returns Err if input is not ascii or empty
+    ///
+    /// Also returns Err if there is no alias which is an exact match
+    pub fn find_value(&self, input: &str) -> Result<&[Candidate]> {
+        let idx = self.find_leaf(input)?;
+        self.candidates(idx)
+            .ok_or_else(|| anyhow::anyhow!("No leaf with exact match was found"))
+    }
 
-            match &cur_node.content {
-                TrieNodeContent::Leaf { .. } => break, //if leaf, end
+    /// Searches for the terminal node of the alias "input", and returns its index
+    ///
+    /// Returns Err if input is not ascii or empty
+    ///
+    /// Also returns Err if there is no alias which is an exact match
+    pub fn find_leaf(&self, input: &str) -> Result<usize> {
+        if input.is_empty() || !input.is_ascii() {
+            return Err(anyhow::anyhow!("Input string is empty or is not ASCII"));
+        }
 
-                TrieNodeContent::Internal { children } => {
-                    //if internal, try finding a match among children
-                    for child in children {
-                        let ch_chars = &self.nodes[*child].value;
-                        //child may have same value as parent! But we don't care because the parent is a better match
-                        if ch_chars.len() > chidx && input[chidx] == ch_chars[chidx] {
-                            chidx += 1;
-                            ndidx = *child;
-                            continue 'outer;
-                        }
-                    }
-                    //no match was found!
+        let (midx, mlen) = self.find_max_match(input.as_bytes());
+        let node = &self.nodes[midx];
+        if mlen != input.len() || node.depth as usize != mlen || node.terminal().is_none() {
+            return Err(anyhow::anyhow!("No leaf with exact match was found"));
+        }
+        Result::Ok(midx)
+    }
+
+    /// Removes a candidate from the alias "input"
+    ///
+    /// An alias left without candidates is removed along with the nodes only it needed (its bytes stay in `keys`).
+    /// The infix and word indices are updated, so they need not be built again
+    ///
+    /// Returns Err if there is no such alias or candidate
+    pub fn remove_candidate(&mut self, input: &str, value: char, dataset: usize) -> Result<()> {
+        let idx = self.find_leaf(input)?;
+        let t = self.nodes[idx].terminal().unwrap();
+        let pos = self
+            .candidates
+            .get(t)
+            .iter()
+            .position(|c| c.value == value && c.dataset == dataset)
+            .ok_or_else(|| anyhow::anyhow!("No such candidate was found"))?;
+        let candidates = &mut self.candidates.to_mut()[t];
+        candidates.remove(pos);
+        let emptied = candidates.is_empty();
+        //the word index refers to candidates by position
+        let (node, pos) = (idx as u32, pos as u32);
+        let word_index = self.word_index.to_mut();
+        word_index.retain(|e| (e[0], e[1]) != (node, pos));
+        for e in word_index.iter_mut().filter(|e| e[0] == node && e[1] > pos) {
+            e[1] -= 1;
+        }
+        if emptied {
+            self.infix_index.to_mut().retain(|e| e[0] != node);
+            self.remove_terminal(idx);
+            self.prune(idx);
+        }
+        Ok(())
+    }
+
+    // Unmarks a terminal node and drops its candidate list, moving the last list in its place
+    fn remove_terminal(&mut self, idx: usize) {
+        let nodes = self.nodes.to_mut();
+        let t = std::mem::replace(&mut nodes[idx].terminal, NOT_TERMINAL);
+        let candidates = self.candidates.to_mut();
+        let last = (candidates.len() - 1) as u32;
+        candidates.swap_remove(t as usize);
+        if t != last {
+            let owner = nodes.iter_mut().find(|n| n.terminal == last);
+            owner.unwrap().terminal = t;
+        }
+    }
+
+    // Removes a node that is neither terminal nor branching, then its parent if it is no longer needed either
+    fn prune(&mut self, mut idx: usize) {
+        while idx != 0 && self.nodes[idx].terminal().is_none() {
+            let parent = self.nodes[idx].parent as usize;
+            let first = self.label(idx)[0];
+            let pos = self
+                .children
+                .get(parent)
+                .binary_search_by_key(&first, |e| e.byte)
+                .unwrap();
+            match *self.children.get(idx) {
+                [] => {
+                    //a leaf: detach it and go on with the parent
+                    self.children.to_mut()[parent].remove(pos);
+                    let moved = self.remove_node(idx);
+                    idx = if moved == parent { idx } else { parent };
+                }
+                [Edge { node: child, .. }] => {
+                    //a single child takes the place of the node. Paths are contiguous in keys, so its label just grows
+                    let nodes = self.nodes.to_mut();
+                    let label_len = nodes[idx].label_len;
+                    let child_node = &mut nodes[child as usize];
+                    child_node.label_len += label_len;
+                    child_node.parent = parent as u32;
+                    self.children.to_mut()[parent][pos].node = child;
+                    self.remove_node(idx);
                     break;
                 }
+                _ => break,
             }
         }
+    }
 
-        // we return chidx because it equals the length of max matching substring
-        (ndidx, chidx)
+    // Removes a node nothing refers to, moving the last node in its place and updating the references to it
+    // Returns the former index of the moved node
+    fn remove_node(&mut self, idx: usize) -> usize {
+        let nodes = self.nodes.to_mut();
+        let children = self.children.to_mut();
+        let last = nodes.len() - 1;
+        nodes.swap_remove(idx);
+        children.swap_remove(idx);
+        if idx != last {
+            let (from, to) = (last as u32, idx as u32);
+            let parent = nodes[idx].parent as usize;
+            for e in children[parent].iter_mut().filter(|e| e.node == from) {
+                e.node = to;
+            }
+            for e in &children[idx] {
+                nodes[e.node as usize].parent = to;
+            }
+            for e in self
+                .infix_index
+                .to_mut()
+                .iter_mut()
+                .filter(|e| e[0] == from)
+            {
+                e[0] = to;
+            }
+            for e in self.word_index.to_mut().iter_mut().filter(|e| e[0] == from) {
+                e[0] = to;
+            }
+        }
+        last
     }
 
-    /// Searches for the leaf with value "input", and returns its value
+    /// Finds all aliases that match "input" when case and separators (`_`, `-`) are ignored
     ///
-    /// Returns Err if input is not ascii or empty
+    /// Unlike `find_max_match`, only aliases whose normalized value starts with the normalized input are returned.
+    /// Aliases are ordered by the number of case mismatches against the input (so `gamma` ranks γ above Γ), then in tree order.
     ///
-    /// Also returns Err if there is no leaf which is an exact match
-    pub fn find_value(&self, input: &str) -> Result<char> {
-        if input.len() == 0 || !input.is_ascii() {
-            return Err(anyhow::anyhow!("Input string is empty or is not ASCII"));
+    /// Return type = Vec<(idx : usize, len : usize)>, where len is the length of the matching prefix of the alias
+    pub fn find_normalized_matches(&self, input: &[u8]) -> Vec<(usize, usize)> {
+        let query = normalize_alias(input);
+        let mut result: Vec<(usize, usize, usize)> = Vec::new(); //idx, len, case mismatches
+        if query.is_empty() {
+            return Vec::new();
         }
 
-        let (midx, mlen) = self.find_max_match(input.as_bytes());
-        if mlen != input.len() {
-            return Err(anyhow::anyhow!("No leaf with exact match was found"));
+        //depth-first search over nodes whose normalized path is compatible with the query,
+        //along with the number of query bytes matched by the path of the parent.
+        //Labels are normalized byte by byte, so nothing is allocated per node
+        let mut stack: Vec<(usize, usize)> = vec![(0, 0)];
+        'nodes: while let Some((ndidx, mut matched)) = stack.pop() {
+            for &b in self.label(ndidx) {
+                if matched == query.len() {
+                    break;
+                }
+                if is_separator(b) {
+                    continue;
+                }
+                if b.to_ascii_lowercase() != query[matched] {
+                    continue 'nodes;
+                }
+                matched += 1;
+            }
+            if matched == query.len() {
+                //every alias under this node matches
+                for (idx, _) in self.iter(ndidx) {
+                    if self.nodes[idx].terminal().is_some() {
+                        let (len, mismatches) = normalized_match_len(self.value(idx), input);
+                        result.push((idx, len, mismatches));
+                    }
+                }
+            } else {
+                //push in reverse so that children are visited in order
+                let children = self.children.get(ndidx);
+                stack.extend(children.iter().rev().map(|e| (e.node as usize, matched)));
+            }
         }
-        match &self.nodes[midx].content {
-            TrieNodeContent::Internal { children } => {
-                for cidx in children {
-                    if self.nodes[*cidx].value.len() == mlen {
-                        if let TrieNodeContent::Leaf { data } = &self.nodes[*cidx].content {
-                            return Result::Ok(*data);
-                        } else {
-                            panic!("An internal node with the same name as its parent was found")
+
+        result.sort_by_key(|&(_, _, mismatches)| mismatches);
+        result.into_iter().map(|(idx, len, _)| (idx, len)).collect()
+    }
+
+    /// Finds aliases that start with a misspelling of "input", for suggesting corrections
+    ///
+    /// The distance is the Damerau-Levenshtein distance (with adjacent transpositions) between the input and
+    /// the shortest prefix of the alias that is at least as long as the input and within max_distance of it,
+    /// or the whole alias if it is shorter than the input.
+    /// So a trailing typo is corrected rather than dropped (`alphz` finds `alpha`, but not `alph_b`)
+    ///
+    /// If normalized, case is folded and separators (`_`, `-`) are ignored as in `find_normalized_matches`,
+    /// and lengths are compared without separators
+    ///
+    /// Return type = Vec<(idx : usize, len : usize, distance : usize)>, in tree order, with distance <= max_distance.
+    /// If the first len bytes of the alias are at least as long as the input, every alias under the node idx starts with them.
+    /// Otherwise idx is a whole alias shorter than the input
+    pub fn find_similar(
+        &self,
+        input: &[u8],
+        max_distance: usize,
+        normalized: bool,
+    ) -> Vec<(usize, usize, usize)> {
+        let input = if normalized {
+            normalize_alias(input)
+        } else {
+            input.to_vec()
+        };
+        //the byte compared for a byte of the path, None if it is skipped
+        let key = |b: u8| match normalized {
+            true if is_separator(b) => None,
+            true => Some(b.to_ascii_lowercase()),
+            false => Some(b),
+        };
+        let n = input.len();
+        let mut result = Vec::new();
+        //rows[d] = row of the first d bytes of the path
+        let mut rows: Vec<SimilarRow> = vec![SimilarRow {
+            dist: (0..=n).collect(),
+            len: 0,
+            last: 0,
+        }];
+        //depth-first search, pushing children in reverse so that they are visited in order
+        let mut stack: Vec<usize> = vec![0];
+        'nodes: while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            let depth = node.depth as usize;
+            let start = depth - node.label_len as usize;
+            let path = self.value(idx);
+            rows.truncate(start + 1);
+            for d in start + 1..=depth {
+                let prev = &rows[d - 1];
+                let Some(c) = key(path[d - 1]) else {
+                    //a skipped byte changes nothing
+                    let row = SimilarRow {
+                        dist: prev.dist.clone(),
+                        len: prev.len,
+                        last: prev.last,
+                    };
+                    rows.push(row);
+                    continue;
+                };
+                //the previous compared byte, for transpositions
+                let before = (prev.last > 0).then(|| (key(path[prev.last - 1]), prev.last - 1));
+                let mut dist = vec![prev.len + 1; n + 1];
+                for j in 1..=n {
+                    let cost = (input[j - 1] != c) as usize;
+                    dist[j] = min(min(prev.dist[j], dist[j - 1]) + 1, prev.dist[j - 1] + cost);
+                    if let Some((b, pd)) = before {
+                        if j > 1 && b == Some(input[j - 1]) && input[j - 2] == c {
+                            dist[j] = min(dist[j], rows[pd].dist[j - 2] + 1);
                         }
                     }
                 }
-                Err(anyhow::anyhow!("No leaf with exact match was found"))
+                let len = prev.len + 1;
+                let (to_input, best) = (dist[n], *dist.iter().min().unwrap());
+                rows.push(SimilarRow { dist, len, last: d });
+                if len >= n && to_input <= max_distance {
+                    result.push((idx, d, to_input));
+                    continue 'nodes;
+                }
+                if best > max_distance {
+                    //every longer path is farther
+                    continue 'nodes;
+                }
+            }
+            let row = &rows[depth];
+            if node.terminal().is_some() && row.len < n && row.dist[n] <= max_distance {
+                result.push((idx, depth, row.dist[n]));
             }
-            TrieNodeContent::Leaf { data } => Result::Ok(*data),
+            stack.extend(self.children.get(idx).iter().rev().map(|e| e.node as usize));
         }
+        result
     }
 
-    /// Appends a leaf and creates internal nodes if needed
+    /// Appends an alias and creates nodes if needed
     ///
     /// Value must be given as a nonempty ASCII string
     ///
-    /// If the same leaf already exists the input will be ignored
+    /// If the alias already exists, data is added to it as another candidate.
+    /// If the alias already has the same candidate (value and dataset) the input will be ignored.
+    /// Other datasets may map the alias to the same value, each with its own candidate
     ///
-    /// If a leaf has the same value as its parent, it is always set as the first item and is assumed to be so.
-    pub fn append_leaf(&mut self, input: String, data: char) -> Result<()> {
-        assert!(input.len() > 0, "Value must be a non-empty string");
-        assert!(input.is_ascii(), "Value must be an ASCII string");
-
-        let in_chars = input.into_boxed_str().into_boxed_bytes();
-        let (ndidx, match_len) = self.find_max_match(&in_chars);
-        //case 1: internal && inclusion(match node value is strictly contained inside input string) -> add leaf as child of the match node
-        //this is also the case when the match node is the root
-        //case 2: internal && match node value = input string -> check is there is a child of same value. If not, add the leaf at front, else error
-        //case 3: internal && conflict || leaf && nonperfect match -> add new node with matching substring and add the new leaf and matching node as children
-        //case 4: leaf && perfect match -> error
-
-        let match_node = &self.nodes[ndidx];
-
-        match &match_node.content {
-            TrieNodeContent::Internal { children } => {
-                if match_len == match_node.value.len() {
-                    //case 1 & case 2
-                    //if there is a child with the same value, we return an error
-                    if match_len == in_chars.len()
-                        && children.len() > 0
-                        && self.nodes[children[0]].value.len() == match_len
-                    {
-                        return Err(anyhow::anyhow!("Leaf with same value already exists"));
-                    }
-                    let case2 = match_len == in_chars.len();
-                    //add the leaf as a child of the match node
-                    let leaf_idx = self.nodes.len();
-                    self.nodes.push(TrieNode {
-                        value: in_chars,
-                        parent: ndidx,
-                        content: TrieNodeContent::Leaf { data },
-                    });
-                    //if the match node is a perfect match, we add the leaf at the front
-                    if case2 {
-                        self.nodes[ndidx].try_add_child_front(leaf_idx);
-                    } else {
-                        (self.nodes[ndidx].try_add_child(leaf_idx));
-                    }
-                    return Ok(());
+    /// Dataset is the index returned by `add_dataset`
+    ///
+    /// Returns Err if the input is empty or not ASCII, if the dataset does not exist or if the candidate already exists
+    pub fn append_leaf(&mut self, input: String, data: char, dataset: usize) -> Result<()> {
+        let data = Candidate {
+            value: data,
+            dataset,
+            description: None,
+        };
+        self.append_candidate(input, data)
+    }
+
+    /// Same as `append_leaf`, for a candidate that may have a description
+    ///
+    /// Also returns Err if the description does not exist
+    pub fn append_candidate(&mut self, input: String, data: Candidate) -> Result<()> {
+        if input.is_empty() || !input.is_ascii() {
+            return Err(anyhow::anyhow!("Input string is empty or is not ASCII"));
+        }
+        if data.dataset >= self.datasets.len() {
+            return Err(anyhow::anyhow!("Dataset {} does not exist", data.dataset));
+        }
+        if data
+            .description
+            .is_some_and(|d| d.get() as usize > self.descriptions.len())
+        {
+            return Err(anyhow::anyhow!(
+                "Description {:?} does not exist",
+                data.description
+            ));
+        }
+
+        let in_chars = input.as_bytes();
+        let (mut ndidx, match_len) = self.find_max_match(in_chars);
+        //case 1: the match ends inside the label -> split the node, so that a node ends at the match
+        //case 2: the input goes on after the node -> add a node with the rest of the input as a child
+        //then the node at ndidx is the one of the input -> add candidate
+        if match_len < self.nodes[ndidx].depth as usize {
+            ndidx = self.split(ndidx, match_len);
+        }
+        if match_len < in_chars.len() {
+            ndidx = self.add_child(ndidx, in_chars);
+        }
+        self.add_candidate(ndidx, data)
+    }
+
+    // Splits a node so that a new node ends at the given depth of its path
+    // Returns the index of the new node, which takes the place of the old one under its parent
+    fn split(&mut self, idx: usize, depth: usize) -> usize {
+        let node = &self.nodes[idx];
+        let cut = node.depth - depth as u32; //length of the label left to the old node
+        let parent = node.parent as usize;
+        let first = self.label(idx)[0];
+        let inter_idx = self.nodes.len() as u32;
+        //the label of the new node is the start of the old label, so it is a slice of the same key
+        let inter_node = TrieNode {
+            label_end: node.label_end - cut,
+            label_len: node.label_len - cut,
+            depth: depth as u32,
+            parent: node.parent,
+            terminal: NOT_TERMINAL,
+        };
+        let inter_edge = Edge::new(self.keys[(node.label_end - cut) as usize], idx as u32);
+        let nodes = self.nodes.to_mut();
+        nodes.push(inter_node);
+        let node = &mut nodes[idx];
+        node.label_len = cut;
+        node.parent = inter_idx;
+        let all_children = self.children.to_mut();
+        all_children.push(vec![inter_edge]);
+        //replace the old node with the new node (both labels start with the same byte)
+        let children = &mut all_children[parent];
+        let pos = children.binary_search_by_key(&first, |e| e.byte).unwrap();
+        children[pos].node = inter_idx;
+        inter_idx as usize
+    }
+
+    // Adds a node for the rest of the key below a node whose path is a prefix of the key
+    // The key is stored whole, so that the path of the new node is contiguous
+    // Returns the index of the new node
+    fn add_child(&mut self, idx: usize, key: &[u8]) -> usize {
+        let depth = self.nodes[idx].depth as usize;
+        let keys = self.keys.to_mut();
+        keys.extend_from_slice(key);
+        let child_idx = self.nodes.len() as u32;
+        self.nodes.to_mut().push(TrieNode {
+            label_end: keys.len() as u32,
+            label_len: (key.len() - depth) as u32,
+            depth: key.len() as u32,
+            parent: idx as u32,
+            terminal: NOT_TERMINAL,
+        });
+        let all_children = self.children.to_mut();
+        all_children.push(Vec::new());
+        let children = &mut all_children[idx];
+        let pos = children
+            .binary_search_by_key(&key[depth], |e| e.byte)
+            .unwrap_err();
+        children.insert(pos, Edge::new(key[depth], child_idx));
+        child_idx as usize
+    }
+
+    // Adds a candidate to the node, marking it as terminal
+    // Returns Err if the candidate already exists in the same dataset
+    fn add_candidate(&mut self, idx: usize, candidate: Candidate) -> Result<()> {
+        match self.nodes[idx].terminal() {
+            Some(t) => {
+                let data = &mut self.candidates.to_mut()[t];
+                if data
+                    .iter()
+                    .any(|c| c.value == candidate.value && c.dataset == candidate.dataset)
+                {
+                    return Err(anyhow::anyhow!(
+                        "Leaf with same value and candidate already exists"
+                    ));
+                }
+                data.push(candidate);
+            }
+            None => {
+                let candidates = self.candidates.to_mut();
+                self.nodes.to_mut()[idx].terminal = candidates.len() as u32;
+                candidates.push(vec![candidate]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends a candidate like `append_candidate`, and adds it to the infix and word indices
+    /// so that a single insertion does not need `build_infix_index`
+    pub fn insert_candidate(&mut self, input: String, candidate: Candidate) -> Result<()> {
+        self.append_candidate(input.clone(), candidate)?;
+        let idx = self.find_leaf(&input)?;
+        let pos = self.candidates(idx).unwrap().len() - 1;
+        if pos == 0 {
+            //a new alias: index its proper suffixes
+            for offset in 1..self.nodes[idx].depth {
+                let entry = [idx as u32, offset];
+                let suffix = |&[i, o]: &[u32; 2]| &self.value(i as usize)[o as usize..];
+                let at = self
+                    .infix_index
+                    .partition_point(|e| suffix(e) <= suffix(&entry));
+                self.infix_index.to_mut().insert(at, entry);
+            }
+        }
+        if let Some(text) = self.description(&candidate) {
+            let starts: Vec<usize> = word_starts(text).collect();
+            for offset in starts {
+                let entry = [idx as u32, pos as u32, offset as u32];
+                let word = |e: &[u32; 3]| fold_case(self.described_word(e));
+                let at = self
+                    .word_index
+                    .partition_point(|e| word(e).le(word(&entry)));
+                self.word_index.to_mut().insert(at, entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the suffix index used by `find_infix_matches`, and the word index used by `find_description_matches`
+    ///
+    /// Every proper suffix of every alias is indexed (the whole alias is already covered by prefix search).
+    /// The index is not updated by `append_leaf`, so this should be called again after appending aliases
+    /// (unless they are appended by `insert_candidate`)
+    pub fn build_infix_index(&mut self) {
+        self.build_word_index();
+        let mut index = Vec::new();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if node.terminal().is_some() {
+                for offset in 1..node.depth {
+                    index.push([idx as u32, offset]);
                 }
-                //else: continue on to case 3
             }
-            TrieNodeContent::Leaf { .. } => {
-                if match_len == in_chars.len() {
-                    if match_len == match_node.value.len() {
-                        //perfect match
-                        return Err(anyhow::anyhow!("Leaf with same value already exists"));
+        }
+        index.sort_by(|&[a, aoff], &[b, boff]| {
+            self.value(a as usize)[aoff as usize..].cmp(&self.value(b as usize)[boff as usize..])
+        });
+        self.infix_index = Array::Owned(index);
+    }
+
+    // Indexes every word start of every description of a candidate
+    fn build_word_index(&mut self) {
+        let mut index = Vec::new();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let Some(t) = node.terminal() else {
+                continue;
+            };
+            for (pos, candidate) in self.candidates.get(t).iter().enumerate() {
+                if let Some(text) = self.description(candidate) {
+                    for offset in word_starts(text) {
+                        index.push([idx as u32, pos as u32, offset as u32]);
                     }
                 }
             }
+        }
+        index.sort_by(|a, b| {
+            fold_case(self.described_word(a)).cmp(fold_case(self.described_word(b)))
+        });
+        self.word_index = Array::Owned(index);
+    }
+
+    // The description of an entry of the word index, starting at the word
+    fn described_word(&self, &[idx, pos, offset]: &[u32; 3]) -> &str {
+        let t = self.nodes[idx as usize].terminal().unwrap();
+        let candidate = &self.candidates.get(t)[pos as usize];
+        &self.description(candidate).unwrap()[offset as usize..]
+    }
+
+    /// Finds the candidates whose description has words starting with every word of "input", ignoring ASCII case
+    ///
+    /// Words of the input are separated by whitespace or `_` (ex. `left_corner` finds "left corner bracket").
+    /// Candidates are ordered by their alias, then by their position among the candidates of the alias
+    ///
+    /// Return type = Vec<(idx : usize, candidate index : usize)>
+    pub fn find_description_matches(&self, input: &str) -> Vec<(usize, usize)> {
+        let mut words = input
+            .split(|c: char| c.is_whitespace() || c == '_')
+            .filter(|w| !w.is_empty());
+        let Some(first) = words.next() else {
+            return Vec::new();
         };
-        //case 3
-        let inter_value = match_node.value[..match_len].to_vec().into_boxed_slice();
-        let par_idx = match_node.parent;
-        let inter_node = TrieNode {
-            value: inter_value,
-            parent: par_idx,
-            content: TrieNodeContent::Internal {
-                children: vec![ndidx],
-            },
-        };
-        //add the new intemidate node
-        let inter_idx = self.nodes.len();
-        let add_new_front = match_len == in_chars.len();
-        self.nodes.push(inter_node);
-        //replace the old node with the new intermediate node
-        self.nodes[par_idx].try_remove_child(ndidx);
-        self.nodes[par_idx].try_add_child(inter_idx);
-
-        //add the new leaf
-        let leaf_idx = self.nodes.len();
-        self.nodes.push(TrieNode {
-            value: in_chars,
-            parent: inter_idx,
-            content: TrieNodeContent::Leaf { data },
+        let rest: Vec<&str> = words.collect();
+        //binary search for the first word not less than the first word of the input. Words starting with it come right after
+        let start = self
+            .word_index
+            .partition_point(|e| fold_case(self.described_word(e)).lt(fold_case(first)));
+        let mut result: Vec<(usize, usize)> = Vec::new();
+        for e in &self.word_index[start..] {
+            if !starts_with_folded(self.described_word(e), first) {
+                break;
+            }
+            result.push((e[0] as usize, e[1] as usize));
+        }
+        result.sort();
+        result.dedup();
+        result.retain(|&(idx, pos)| {
+            let t = self.nodes[idx].terminal().unwrap();
+            let text = self.description(&self.candidates.get(t)[pos]).unwrap();
+            rest.iter()
+                .all(|w| word_starts(text).any(|offset| starts_with_folded(&text[offset..], w)))
         });
-        //add the new leaf and intermediate node as children of the parent
-        if add_new_front {
-            self.nodes[inter_idx].try_add_child_front(leaf_idx);
-        } else {
-            self.nodes[inter_idx].try_add_child(leaf_idx);
+        result.sort_by(|&(a, apos), &(b, bpos)| {
+            self.value(a).cmp(self.value(b)).then(apos.cmp(&bpos))
+        });
+        result
+    }
+
+    /// Finds all aliases containing "input" after their first character
+    ///
+    /// Each alias appears once with the offset of its first occurrence.
+    /// Aliases are ordered by the offset, then by their length
+    ///
+    /// Return type = Vec<(idx : usize, offset : usize)>
+    pub fn find_infix_matches(&self, input: &[u8]) -> Vec<(usize, usize)> {
+        if input.is_empty() {
+            return Vec::new();
         }
-        self.nodes[ndidx].parent = inter_idx;
-        Ok(())
+        let suffix = |&[idx, offset]: &[u32; 2]| &self.value(idx as usize)[offset as usize..];
+        //binary search for the first suffix not less than input. Suffixes starting with input come right after
+        let start = self.infix_index.partition_point(|e| suffix(e) < input);
+        //the index lists every occurrence, so each alias is kept once (marked in a bitset)
+        //with its first occurrence searched in the alias itself
+        let mut seen = vec![0u64; self.nodes.len().div_ceil(64)];
+        let mut result: Vec<(usize, usize)> = Vec::new();
+        for &[idx, offset] in &self.infix_index[start..] {
+            if !suffix(&[idx, offset]).starts_with(input) {
+                break;
+            }
+            let idx = idx as usize;
+            if seen[idx / 64] & (1 << (idx % 64)) != 0 {
+                continue;
+            }
+            seen[idx / 64] |= 1 << (idx % 64);
+            let value = self.value(idx);
+            let first = (1..offset as usize)
+                .find(|&o| value[o..].starts_with(input))
+                .unwrap_or(offset as usize);
+            result.push((idx, first));
+        }
+        result.sort_unstable_by_key(|&(idx, offset)| (offset, self.nodes[idx].depth, idx));
+        result
     }
 
-    /// Returns an iterator over all nodes
+    /// Returns an iterator over a node and all of its descendants
     ///
-    /// It gets the index by a refernce, but it won't be much of a nuisance when using
-    pub fn iter<'a>(&'a self, node_idx: &'a usize) -> TrieIter<'a> {
-        return TrieIter {
-            trie: &self,
-            desc_stack: vec![std::slice::from_ref(node_idx).into_iter()],
+    /// Children are visited in byte order, so aliases come in lexicographic order
+    pub fn iter(&self, node_idx: usize) -> TrieIter<'_> {
+        TrieIter {
+            trie: self,
+            start: Some(node_idx),
+            desc_stack: Vec::new(),
+        }
+    }
+
+    /// Checks the invariants of the trie, returning the first violation found
+    ///
+    /// - every node is reachable from the root exactly once, and knows its parent
+    /// - children are sorted by the first byte of their labels, which is stored along with them
+    /// - labels are non-empty (except the root's), and the path of a child extends the path of its parent
+    /// - nodes which are neither terminal nor the root have at least two children
+    /// - every candidate list belongs to exactly one terminal node, and refers to a registered dataset
+    /// - the infix index is sorted and refers to terminal nodes (it may be out of date after `append_leaf`)
+    ///
+    /// It does not panic on any values of the arrays, so that a corrupted cache is detected
+    pub fn validate(&self) -> Result<()> {
+        let Some(root) = self.nodes.first() else {
+            return Err(anyhow::anyhow!("Trie has no root"));
         };
+        if root.depth != 0 || root.label_len != 0 {
+            return Err(anyhow::anyhow!("Root node has a label"));
+        }
+        if self.children.len() != self.nodes.len() {
+            return Err(anyhow::anyhow!("Trie does not have a child list per node"));
+        }
+        if !self.keys.is_ascii() {
+            return Err(anyhow::anyhow!("Keys are not ASCII"));
+        }
+        //labels are checked first, so that they can be sliced below
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if node.label_end as usize > self.keys.len() || node.label_end < node.depth {
+                return Err(anyhow::anyhow!("Label of node {} is out of bounds", idx));
+            }
+            if node.label_len > node.depth {
+                return Err(anyhow::anyhow!(
+                    "Label of node {} is longer than its path",
+                    idx
+                ));
+            }
+        }
+        let mut visited = vec![false; self.nodes.len()];
+        let mut terminals = vec![false; self.candidates.len()];
+        //depth-first search that checks the indices before following them
+        let mut stack: Vec<usize> = vec![0];
+        while let Some(idx) = stack.pop() {
+            if std::mem::replace(&mut visited[idx], true) {
+                return Err(anyhow::anyhow!("Node {} is reachable more than once", idx));
+            }
+            let node = &self.nodes[idx];
+            let children = self.children.get(idx);
+            if idx != 0 && node.terminal().is_none() && children.len() < 2 {
+                return Err(anyhow::anyhow!(
+                    "Node {} is neither terminal nor branching",
+                    idx
+                ));
+            }
+            if let Some(t) = node.terminal() {
+                match terminals.get_mut(t) {
+                    Some(seen) if !*seen => *seen = true,
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "Node {} has an invalid or shared candidate list {}",
+                            idx,
+                            t
+                        ))
+                    }
+                }
+                if let Some(c) = self
+                    .candidates
+                    .get(t)
+                    .iter()
+                    .find(|c| c.dataset >= self.datasets.len())
+                {
+                    return Err(anyhow::anyhow!(
+                        "Candidate {:?} of node {} has an unknown dataset",
+                        c,
+                        idx
+                    ));
+                }
+                if let Some(c) = self
+                    .candidates
+                    .get(t)
+                    .iter()
+                    .find(|c| c.description.is_some() && self.description(c).is_none())
+                {
+                    return Err(anyhow::anyhow!(
+                        "Candidate {:?} of node {} has an unknown description",
+                        c,
+                        idx
+                    ));
+                }
+            }
+            for (
+                pos,
+                &Edge {
+                    byte,
+                    node: child_idx,
+                    ..
+                },
+            ) in children.iter().enumerate()
+            {
+                let Some(child) = self.nodes.get(child_idx as usize) else {
+                    return Err(anyhow::anyhow!("Node {} has an invalid child", idx));
+                };
+                if pos > 0 && children[pos - 1].byte >= byte {
+                    return Err(anyhow::anyhow!("Children of node {} are not sorted", idx));
+                }
+                if child.parent as usize != idx {
+                    return Err(anyhow::anyhow!(
+                        "Node {} is not the parent of its child {}",
+                        idx,
+                        child_idx
+                    ));
+                }
+                if child.label_len == 0
+                    || Some(child.depth) != node.depth.checked_add(child.label_len)
+                {
+                    return Err(anyhow::anyhow!(
+                        "Child {} of node {} has an invalid depth",
+                        child_idx,
+                        idx
+                    ));
+                }
+                let label = self.label(child_idx as usize);
+                if label[0] != byte {
+                    return Err(anyhow::anyhow!(
+                        "Child {} of node {} is stored with the wrong byte",
+                        child_idx,
+                        idx
+                    ));
+                }
+                if !self.value(child_idx as usize).starts_with(self.value(idx)) {
+                    return Err(anyhow::anyhow!(
+                        "Path of child {} does not extend the path of node {}",
+                        child_idx,
+                        idx
+                    ));
+                }
+                stack.push(child_idx as usize);
+            }
+        }
+        if let Some(idx) = visited.iter().position(|v| !v) {
+            return Err(anyhow::anyhow!("Node {} is not reachable", idx));
+        }
+        if let Some(t) = terminals.iter().position(|t| !t) {
+            return Err(anyhow::anyhow!("Candidate list {} has no node", t));
+        }
+        for &[idx, offset] in self.infix_index.iter() {
+            match self.nodes.get(idx as usize) {
+                Some(node) if node.terminal().is_some() && offset > 0 && offset < node.depth => {}
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Infix index entry ({}, {}) is invalid",
+                        idx,
+                        offset
+                    ))
+                }
+            }
+        }
+        let suffix = |&[idx, offset]: &[u32; 2]| &self.value(idx as usize)[offset as usize..];
+        if self
+            .infix_index
+            .windows(2)
+            .any(|w| suffix(&w[0]) > suffix(&w[1]))
+        {
+            return Err(anyhow::anyhow!("Infix index is not sorted"));
+        }
+        for &[idx, pos, offset] in self.word_index.iter() {
+            let text = self
+                .nodes
+                .get(idx as usize)
+                .and_then(|node| node.terminal())
+                .and_then(|t| self.candidates.get(t).get(pos as usize))
+                .and_then(|c| self.description(c));
+            if !text.is_some_and(|text| word_starts(text).any(|o| o == offset as usize)) {
+                return Err(anyhow::anyhow!(
+                    "Word index entry ({}, {}, {}) is invalid",
+                    idx,
+                    pos,
+                    offset
+                ));
+            }
+        }
+        if self.word_index.windows(2).any(|w| {
+            fold_case(self.described_word(&w[0])).gt(fold_case(self.described_word(&w[1])))
+        }) {
+            return Err(anyhow::anyhow!("Word index is not sorted"));
+        }
+        Ok(())
+    }
+
+    /// Approximate number of bytes used by the trie, including its heap allocations
+    ///
+    /// Arrays borrowed from a mapped cache are not counted
+    pub fn memory_usage(&self) -> usize {
+        use std::mem::size_of;
+        size_of::<Trie>()
+            + self.nodes.heap_size()
+            + self.children.heap_size()
+            + self.keys.heap_size()
+            + self.candidates.heap_size()
+            + self.datasets.iter().map(|d| d.capacity()).sum::<usize>()
+            + self.infix_index.heap_size()
+            + self.descriptions.heap_size()
+            + self.word_index.heap_size()
+    }
+}
+
+/// Normalizes an alias for matching: folds ASCII case and removes separators (`_`, `-`)
+pub fn normalize_alias(value: &[u8]) -> Vec<u8> {
+    value
+        .iter()
+        .filter(|b| !is_separator(**b))
+        .map(|b| b.to_ascii_lowercase())
+        .collect()
+}
+
+fn is_separator(b: u8) -> bool {
+    b == b'_' || b == b'-'
+}
+
+// Byte offsets of the words of a description (runs of alphanumeric characters)
+fn word_starts(text: &str) -> impl Iterator<Item = usize> + '_ {
+    let mut prev_alnum = false;
+    text.char_indices().filter_map(move |(i, c)| {
+        let start = c.is_alphanumeric() && !prev_alnum;
+        prev_alnum = c.is_alphanumeric();
+        start.then_some(i)
+    })
+}
+
+// The bytes of a text with ASCII case folded
+fn fold_case(text: &str) -> impl Iterator<Item = u8> + '_ {
+    text.bytes().map(|b| b.to_ascii_lowercase())
+}
+
+fn starts_with_folded(text: &str, prefix: &str) -> bool {
+    text.len() >= prefix.len()
+        && text.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+// Returns the number of bytes of "value" needed to cover the normalized "input",
+// along with the number of characters among them that only match when case is folded
+//"value" is assumed to normalize to a string starting with normalized "input"
+fn normalized_match_len(value: &[u8], input: &[u8]) -> (usize, usize) {
+    let mut input_chars = input.iter().filter(|b| !is_separator(**b));
+    let mut mismatches = 0;
+    let mut remaining = input_chars.clone().count();
+    let mut len = 0;
+    for b in value {
+        if remaining == 0 {
+            break;
+        }
+        len += 1;
+        if is_separator(*b) {
+            continue;
+        }
+        if let Some(ib) = input_chars.next() {
+            if ib != b {
+                mismatches += 1;
+            }
+        }
+        remaining -= 1;
     }
+    (len, mismatches)
 }
 
 impl Display for Trie {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let trie_iter = self.iter(&0);
         let indent = "    ";
 
-        for (idx, depth) in trie_iter {
-            let node = &self.nodes[idx];
-            write!(f, "{}{}", &indent.repeat(depth), node.value_str())?; //indentation
-            if let TrieNodeContent::Leaf { data } = node.content {
-                write!(f, "({})", data)?;
+        for (idx, depth) in self.iter(0) {
+            let label = std::str::from_utf8(self.label(idx)).unwrap();
+            write!(f, "{}{}", &indent.repeat(depth), label)?; //indentation
+            if let Some(data) = self.candidates(idx) {
+                write!(f, "({})", data.iter().map(|c| c.value).collect::<String>())?;
             }
-            write!(f, "\n")?; //newline
+            writeln!(f)?; //newline
         }
-        return std::fmt::Result::Ok(());
+        std::fmt::Result::Ok(())
     }
 }
 
+// A row of the edit distance table of `Trie::find_similar`, for the first bytes of a path
+struct SimilarRow {
+    dist: Vec<usize>, //distance to the first j bytes of the input
+    len: usize,       //number of compared bytes (skipped separators are not counted)
+    last: usize,      //number of bytes up to the last compared one
+}
+
 pub struct TrieIter<'a> {
     trie: &'a Trie,
-    desc_stack: Vec<std::slice::Iter<'a, usize>>, //stack of children iterators
+    start: Option<usize>,                        //the node to visit first
+    desc_stack: Vec<std::slice::Iter<'a, Edge>>, //stack of children iterators
 }
 
-//iterates over all descendants (including the ones in the initial desc_stack)
-//depth of root is 0, depth of direct child is 1
+//iterates over the start node and all of its descendants in pre-order
+//depth of the start node is 0, depth of direct child is 1
 impl Iterator for TrieIter<'_> {
     type Item = (usize, usize); //idx, depth
     fn next(&mut self) -> Option<Self::Item> {
-        if self.desc_stack.len() == 0 {
-            return None;
+        if let Some(idx) = self.start.take() {
+            self.desc_stack.push(self.trie.children.get(idx).iter());
+            return Some((idx, 0));
         }
-        //desc_stack.len() >= 1
         loop {
-            if let Some(idx) = self.desc_stack.last_mut().unwrap().next() {
-                let node = &self.trie.nodes[*idx];
-                match &node.content {
-                    TrieNodeContent::Internal { children } => {
-                        self.desc_stack.push(children.iter()); //move further into tree
-                        return Some((*idx, self.desc_stack.len() - 2)); //then return
-                    }
-                    TrieNodeContent::Leaf { .. } => return Some((*idx, self.desc_stack.len() - 1)),
-                }
-            } else {
-                self.desc_stack.pop();
-                if self.desc_stack.len() == 0 {
-                    return None;
+            let children = self.desc_stack.last_mut()?;
+            if let Some(&Edge { node: idx, .. }) = children.next() {
+                let depth = self.desc_stack.len();
+                let children = self.trie.children.get(idx as usize);
+                if !children.is_empty() {
+                    self.desc_stack.push(children.iter()); //move further into tree
                 }
+                return Some((idx as usize, depth));
+            }
+            self.desc_stack.pop();
+        }
+    }
+}
+
+// An array of the trie, either owned or borrowed from a memory-mapped cache
+// Borrowed slices live as long as the map held by the trie, hence the 'static lifetime (see `Trie::from_mapped`)
+enum Array<T: 'static> {
+    Owned(Vec<T>),
+    Mapped(&'static [T]),
+}
+
+impl<T: Clone> Array<T> {
+    // The array as a vector, copying it first if it is borrowed
+    fn to_mut(&mut self) -> &mut Vec<T> {
+        if let Array::Mapped(items) = *self {
+            *self = Array::Owned(items.to_vec());
+        }
+        match self {
+            Array::Owned(items) => items,
+            Array::Mapped(_) => unreachable!(),
+        }
+    }
+
+    // Bytes allocated on the heap (a borrowed array takes none)
+    fn heap_size(&self) -> usize {
+        match self {
+            Array::Owned(items) => items.capacity() * std::mem::size_of::<T>(),
+            Array::Mapped(_) => 0,
+        }
+    }
+}
+
+impl<T> Deref for Array<T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        match self {
+            Array::Owned(items) => items,
+            Array::Mapped(items) => items,
+        }
+    }
+}
+
+// A list per node (or per terminal node), owned or borrowed like `Array`
+// Borrowed lists are stored one after another: list i is items[ends[i - 1]..ends[i]]
+enum Lists<T: 'static> {
+    Owned(Vec<Vec<T>>),
+    Mapped {
+        ends: &'static [u32],
+        items: &'static [T],
+    },
+}
+
+impl<T: Clone> Lists<T> {
+    // Checks that the ends can be sliced with
+    fn mapped(ends: &'static [u32], items: &'static [T]) -> Result<Self> {
+        let sorted = ends.windows(2).all(|w| w[0] <= w[1]);
+        if !sorted || ends.last().is_some_and(|&end| end as usize > items.len()) {
+            return Err(anyhow::anyhow!("Mapped list ends are invalid"));
+        }
+        Ok(Lists::Mapped { ends, items })
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Lists::Owned(lists) => lists.len(),
+            Lists::Mapped { ends, .. } => ends.len(),
+        }
+    }
+
+    // Panics if there is no such list, like indexing
+    fn get(&self, i: usize) -> &[T] {
+        match self {
+            Lists::Owned(lists) => &lists[i],
+            Lists::Mapped { ends, items } => {
+                let start = if i == 0 { 0 } else { ends[i - 1] };
+                &items[start as usize..ends[i] as usize]
+            }
+        }
+    }
+
+    // The lists as vectors, copying them first if they are borrowed
+    fn to_mut(&mut self) -> &mut Vec<Vec<T>> {
+        if let Lists::Mapped { .. } = self {
+            *self = Lists::Owned((0..self.len()).map(|i| self.get(i).to_vec()).collect());
+        }
+        match self {
+            Lists::Owned(lists) => lists,
+            Lists::Mapped { .. } => unreachable!(),
+        }
+    }
+
+    fn heap_size(&self) -> usize {
+        match self {
+            Lists::Owned(lists) => {
+                let items: usize = lists.iter().map(|l| l.capacity()).sum();
+                lists.capacity() * std::mem::size_of::<Vec<T>>() + items * std::mem::size_of::<T>()
+            }
+            Lists::Mapped { .. } => 0,
+        }
+    }
+
+    // The ends and items of the lists, as they are mapped
+    fn flatten(&self) -> (Vec<u32>, Vec<T>) {
+        let (mut ends, mut items) = (Vec::with_capacity(self.len()), Vec::new());
+        for i in 0..self.len() {
+            items.extend_from_slice(self.get(i));
+            ends.push(items.len() as u32);
+        }
+        (ends, items)
+    }
+}
+
+// The descriptions, owned or borrowed like `Lists`. Borrowed text is checked to be UTF-8 once, when mapped
+enum Texts {
+    Owned(Vec<String>),
+    Mapped {
+        ends: &'static [u32],
+        text: &'static str,
+    },
+}
+
+impl Texts {
+    // Checks that the ends can be sliced with
+    fn mapped(ends: &'static [u32], text: &'static [u8]) -> Result<Self> {
+        let text = std::str::from_utf8(text)?;
+        let sorted = ends.windows(2).all(|w| w[0] <= w[1]);
+        if !sorted || !ends.iter().all(|&end| text.is_char_boundary(end as usize)) {
+            return Err(anyhow::anyhow!("Mapped description ends are invalid"));
+        }
+        Ok(Texts::Mapped { ends, text })
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Texts::Owned(texts) => texts.len(),
+            Texts::Mapped { ends, .. } => ends.len(),
+        }
+    }
+
+    fn get(&self, i: usize) -> Option<&str> {
+        match self {
+            Texts::Owned(texts) => texts.get(i).map(|t| t.as_str()),
+            Texts::Mapped { ends, text } => {
+                let end = *ends.get(i)?;
+                let start = if i == 0 { 0 } else { ends[i - 1] };
+                Some(&text[start as usize..end as usize])
             }
         }
     }
+
+    fn to_mut(&mut self) -> &mut Vec<String> {
+        if let Texts::Mapped { .. } = self {
+            *self = Texts::Owned(
+                (0..self.len())
+                    .map(|i| self.get(i).unwrap().to_string())
+                    .collect(),
+            );
+        }
+        match self {
+            Texts::Owned(texts) => texts,
+            Texts::Mapped { .. } => unreachable!(),
+        }
+    }
+
+    fn heap_size(&self) -> usize {
+        match self {
+            Texts::Owned(texts) => {
+                texts.capacity() * std::mem::size_of::<String>()
+                    + texts.iter().map(|t| t.capacity()).sum::<usize>()
+            }
+            Texts::Mapped { .. } => 0,
+        }
+    }
+}
+
+// Writes an array to be read by `MappedArrays`: its length in bytes, then its bytes padded to a multiple of 8
+fn write_array<T: IntoBytes + Immutable>(out: &mut impl Write, items: &[T]) -> io::Result<()> {
+    let bytes = items.as_bytes();
+    out.write_all(&(bytes.len() as u64).to_ne_bytes())?;
+    out.write_all(bytes)?;
+    out.write_all(&[0; 8][..bytes.len().next_multiple_of(8) - bytes.len()])
+}
+
+// Reads the arrays written by `write_array` from a mapped file, in the same order
+struct MappedArrays {
+    bytes: &'static [u8],
+    pos: usize, //a multiple of 8, so that every array is aligned
+}
+
+impl MappedArrays {
+    fn next<T: TryFromBytes + KnownLayout + Immutable>(&mut self) -> Result<&'static [T]> {
+        let truncated = || anyhow::anyhow!("Mapped trie is truncated");
+        let len = self
+            .bytes
+            .get(self.pos..self.pos + 8)
+            .ok_or_else(truncated)?;
+        let len = u64::from_ne_bytes(len.try_into().unwrap()) as usize;
+        let start = self.pos + 8;
+        //the padding is written too, so a file missing it is truncated
+        let end = start
+            .checked_add(len.checked_next_multiple_of(8).ok_or_else(truncated)?)
+            .ok_or_else(truncated)?;
+        let array = self.bytes.get(start..end).ok_or_else(truncated)?;
+        self.pos = end;
+        <[T]>::try_ref_from_bytes(&array[..len])
+            .map_err(|_| anyhow::anyhow!("Mapped trie has a misaligned or invalid array"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a trie of the aliases, each mapped to its first character, checking the invariants after every insert
+    fn trie_of(aliases: &[&str]) -> Trie {
+        let mut trie = Trie::new();
+        let dataset = trie.add_dataset("test");
+        for alias in aliases {
+            let value = alias.chars().next().unwrap();
+            trie.append_leaf(alias.to_string(), value, dataset).unwrap();
+            trie.validate().unwrap();
+        }
+        trie
+    }
+
+    fn aliases(trie: &Trie) -> Vec<&str> {
+        trie.iter(0)
+            .filter(|&(idx, _)| trie.candidates(idx).is_some())
+            .map(|(idx, _)| trie.value_str(idx))
+            .collect()
+    }
+
+    #[test]
+    fn new_alias_below_root() {
+        let trie = trie_of(&["alpha"]);
+        assert_eq!(trie.find_value("alpha").unwrap()[0].value, 'a');
+        assert_eq!(aliases(&trie), ["alpha"]);
+    }
+
+    #[test]
+    fn alias_extending_an_alias() {
+        let trie = trie_of(&["al", "alpha"]);
+        assert!(trie.find_value("al").is_ok());
+        assert!(trie.find_value("alpha").is_ok());
+        assert!(trie.find_value("alp").is_err());
+    }
+
+    #[test]
+    fn alias_splitting_a_label() {
+        // "alpha" and "alps" share "alp", which becomes a branching node
+        let trie = trie_of(&["alpha", "alps"]);
+        assert_eq!(aliases(&trie), ["alpha", "alps"]);
+        assert!(trie.find_value("alp").is_err());
+    }
+
+    #[test]
+    fn alias_ending_at_a_branching_node() {
+        // Used to be the "internal node with the same name as its parent" case
+        let trie = trie_of(&["alpha", "alps", "alp"]);
+        assert_eq!(aliases(&trie), ["alp", "alpha", "alps"]);
+        assert_eq!(trie.find_value("alp").unwrap()[0].value, 'a');
+    }
+
+    #[test]
+    fn alias_ending_inside_a_label() {
+        let trie = trie_of(&["alpha", "al"]);
+        assert_eq!(aliases(&trie), ["al", "alpha"]);
+    }
+
+    #[test]
+    fn same_alias_adds_candidates() {
+        let mut trie = trie_of(&["gamma"]);
+        trie.append_leaf("gamma".to_string(), 'Γ', 0).unwrap();
+        let values: Vec<char> = trie
+            .find_value("gamma")
+            .unwrap()
+            .iter()
+            .map(|c| c.value)
+            .collect();
+        assert_eq!(values, ['g', 'Γ']);
+        // The same candidate is rejected
+        assert!(trie.append_leaf("gamma".to_string(), 'Γ', 0).is_err());
+        trie.validate().unwrap();
+    }
+
+    #[test]
+    fn find_value_rejects_invalid_input() {
+        let trie = trie_of(&["alpha"]);
+        assert!(trie.find_value("").is_err());
+        assert!(trie.find_value("αlpha").is_err());
+        assert!(trie.find_value("alphabet").is_err());
+    }
+
+    #[test]
+    fn append_leaf_rejects_invalid_input() {
+        let mut trie = trie_of(&["alpha"]);
+        assert!(trie.append_leaf(String::new(), 'a', 0).is_err());
+        assert!(trie.append_leaf("αlpha".to_string(), 'a', 0).is_err());
+        assert!(trie.append_leaf("beta".to_string(), 'b', 1).is_err());
+        trie.validate().unwrap();
+    }
+
+    #[test]
+    fn find_max_match_inside_a_label() {
+        let trie = trie_of(&["alpha", "beta"]);
+        let (idx, len) = trie.find_max_match(b"alpine");
+        assert_eq!((trie.value_str(idx), len), ("alpha", 3));
+        assert_eq!(trie.find_max_match(b"xyz"), (0, 0));
+    }
+
+    #[test]
+    fn remove_candidate_prunes_the_alias() {
+        let mut trie = Trie::new();
+        let dataset = trie.add_dataset("test");
+        for (alias, value, description) in [
+            ("alpha", 'a', Some("first letter")),
+            ("alpha", 'α', Some("greek letter")),
+            ("alps", 'p', None),
+            ("alp", 'l', None),
+            ("ralph", 'r', Some("letter r")),
+        ] {
+            let description = description.map(|d| trie.add_description(d));
+            let candidate = Candidate {
+                value,
+                dataset,
+                description,
+            };
+            trie.append_candidate(alias.to_string(), candidate).unwrap();
+        }
+        trie.build_infix_index();
+        let infix = |trie: &Trie, input: &[u8]| -> Vec<(String, usize)> {
+            trie.find_infix_matches(input)
+                .into_iter()
+                .map(|(idx, offset)| (trie.value_str(idx).to_string(), offset))
+                .collect()
+        };
+        let described = |trie: &Trie, input: &str| -> Vec<char> {
+            trie.find_description_matches(input)
+                .into_iter()
+                .map(|(idx, pos)| trie.candidates(idx).unwrap()[pos].value)
+                .collect()
+        };
+
+        // The other candidate of the alias moves up in the word index
+        trie.remove_candidate("alpha", 'a', 0).unwrap();
+        trie.validate().unwrap();
+        assert_eq!(trie.find_value("alpha").unwrap()[0].value, 'α');
+        assert_eq!(described(&trie, "letter"), ['α', 'r']);
+        assert!(described(&trie, "first").is_empty());
+
+        // The last candidate removes the alias, and "alp" and "alps" no longer branch
+        trie.remove_candidate("alpha", 'α', 0).unwrap();
+        trie.validate().unwrap();
+        assert!(trie.find_value("alpha").is_err());
+        assert_eq!(aliases(&trie), ["alp", "alps", "ralph"]);
+        assert_eq!(
+            infix(&trie, b"lp"),
+            [
+                ("alp".to_string(), 1),
+                ("alps".to_string(), 1),
+                ("ralph".to_string(), 2)
+            ]
+        );
+        assert_eq!(described(&trie, "letter"), ['r']);
+
+        // A removed alias in the middle of a path keeps the node for the longer one
+        trie.remove_candidate("alp", 'l', 0).unwrap();
+        trie.remove_candidate("ralph", 'r', 0).unwrap();
+        trie.validate().unwrap();
+        assert_eq!(aliases(&trie), ["alps"]);
+        assert_eq!(infix(&trie, b"lp"), [("alps".to_string(), 1)]);
+        assert!(described(&trie, "letter").is_empty());
+
+        trie.remove_candidate("alps", 'p', 0).unwrap();
+        trie.validate().unwrap();
+        assert!(aliases(&trie).is_empty());
+        assert!(trie.remove_candidate("alps", 'p', 0).is_err());
+    }
+
+    #[test]
+    fn same_candidate_in_another_dataset() {
+        let mut trie = Trie::new();
+        let bundled = trie.add_dataset("greek");
+        let user = trie.add_dataset("user");
+        trie.append_leaf("alpha".to_string(), 'α', bundled).unwrap();
+        assert!(trie.append_leaf("alpha".to_string(), 'α', bundled).is_err());
+        // Each dataset keeps its own candidate, so that either can be removed alone
+        trie.append_leaf("alpha".to_string(), 'α', user).unwrap();
+        let datasets = |trie: &Trie| -> Vec<usize> {
+            trie.find_value("alpha")
+                .unwrap()
+                .iter()
+                .map(|c| c.dataset)
+                .collect()
+        };
+        assert_eq!(datasets(&trie), [bundled, user]);
+        trie.remove_candidate("alpha", 'α', user).unwrap();
+        assert_eq!(datasets(&trie), [bundled]);
+        trie.validate().unwrap();
+    }
+
+    #[test]
+    fn insert_candidate_updates_the_indices() {
+        let mut trie = trie_of(&["rightarrow", "alpha"]);
+        let dataset = trie.datasets.len() - 1;
+        trie.build_infix_index();
+        let described = trie.add_description("Narrow arrow");
+        for (alias, description) in [("narrow", Some(described)), ("arrows", None)] {
+            let candidate = Candidate {
+                value: 'n',
+                dataset,
+                description,
+            };
+            trie.insert_candidate(alias.to_string(), candidate).unwrap();
+            trie.validate().unwrap();
+        }
+        let matches: Vec<(&str, usize)> = trie
+            .find_infix_matches(b"arrow")
+            .into_iter()
+            .map(|(idx, offset)| (trie.value_str(idx), offset))
+            .collect();
+        assert_eq!(matches, [("narrow", 1), ("rightarrow", 5)]);
+        let (idx, _) = trie.find_description_matches("narr")[0];
+        assert_eq!(trie.value_str(idx), "narrow");
+        // A new candidate of an existing alias adds no suffix
+        trie.insert_candidate(
+            "narrow".to_string(),
+            Candidate {
+                value: 'm',
+                dataset,
+                description: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(trie.find_infix_matches(b"arrow").len(), 2);
+        trie.validate().unwrap();
+    }
+
+    #[test]
+    fn iteration_is_lexicographic() {
+        let trie = trie_of(&["beta", "alpha", "b", "alps", "Alpha", "a_b"]);
+        assert_eq!(
+            aliases(&trie),
+            ["Alpha", "a_b", "alpha", "alps", "b", "beta"]
+        );
+    }
+
+    #[test]
+    fn infix_matches() {
+        let mut trie = trie_of(&["rightarrow", "leftarrow", "arrow", "narrow", "barrow_arrow"]);
+        trie.build_infix_index();
+        trie.validate().unwrap();
+        let matches: Vec<(&str, usize)> = trie
+            .find_infix_matches(b"arrow")
+            .into_iter()
+            .map(|(idx, offset)| (trie.value_str(idx), offset))
+            .collect();
+        // "arrow" itself is a prefix match, not an infix one.
+        // Aliases are listed once at their first occurrence, by offset then by length
+        assert_eq!(
+            matches,
+            [
+                ("narrow", 1),
+                ("barrow_arrow", 1),
+                ("leftarrow", 4),
+                ("rightarrow", 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn similar_aliases() {
+        let trie = trie_of(&["alpha", "alphabet", "alps", "al", "beta"]);
+        let similar = |input: &str, max| -> Vec<(&str, usize, usize)> {
+            trie.find_similar(input.as_bytes(), max, false)
+                .into_iter()
+                .map(|(idx, len, dist)| (trie.value_str(idx), len, dist))
+                .collect()
+        };
+        // A trailing typo is corrected, not dropped
+        assert_eq!(similar("alphz", 1), [("alpha", 5, 1)]);
+        // Transposition
+        assert_eq!(similar("aplha", 1), [("alpha", 5, 1)]);
+        // An alias shorter than the input, and a node whose aliases all start with the correction
+        assert_eq!(similar("alx", 1), [("al", 2, 1), ("alp", 3, 1)]);
+        assert_eq!(similar("btea", 1), [("beta", 4, 1)]);
+        assert_eq!(similar("alphxbet", 2), [("alphabet", 8, 1)]);
+        assert!(similar("gamma", 1).is_empty());
+    }
+
+    #[test]
+    fn similar_normalized_aliases() {
+        let trie = trie_of(&["Alpha", "left_arrow", "leftarrow", "beta"]);
+        let similar = |input: &str| -> Vec<(&str, usize, usize)> {
+            trie.find_similar(input.as_bytes(), 1, true)
+                .into_iter()
+                .map(|(idx, len, dist)| (trie.value_str(idx), len, dist))
+                .collect()
+        };
+        // Case is folded and separators are skipped, also around a transposition
+        assert_eq!(similar("alpz"), [("Alpha", 4, 1)]);
+        assert_eq!(
+            similar("Left-arorw"),
+            [("left_arrow", 10, 1), ("leftarrow", 9, 1)]
+        );
+        assert_eq!(similar("beat"), [("beta", 4, 1)]);
+        // Without normalization, the case differences count
+        assert!(trie.find_similar(b"alpz", 1, false).is_empty());
+    }
+
+    #[test]
+    fn description_matches() {
+        let mut trie = Trie::new();
+        let dataset = trie.add_dataset("test");
+        for (alias, value, description) in [
+            ("lcorner", '「', Some("Left corner bracket")),
+            ("rcorner", '」', Some("right corner bracket")),
+            ("langle", '〈', Some("left angle bracket")),
+            ("lcorner", '⌜', Some("top left corner")),
+            ("corner", '⌞', None),
+        ] {
+            let candidate = Candidate {
+                value,
+                dataset,
+                description: description.map(|d| trie.add_description(d)),
+            };
+            trie.append_candidate(alias.to_string(), candidate).unwrap();
+        }
+        trie.build_infix_index();
+        trie.validate().unwrap();
+        let found = |input: &str| -> Vec<char> {
+            trie.find_description_matches(input)
+                .into_iter()
+                .map(|(idx, pos)| trie.candidates(idx).unwrap()[pos].value)
+                .collect()
+        };
+        assert_eq!(found("corner"), ['「', '⌜', '」']);
+        assert_eq!(found("LEFT_corn"), ['「', '⌜']);
+        assert_eq!(found("left br"), ['〈', '「']);
+        // Words match from their start
+        assert_eq!(found("orner"), []);
+        assert_eq!(found("_"), []);
+        assert_eq!(
+            trie.description(&trie.find_value("corner").unwrap()[0]),
+            None
+        );
+
+        let unknown = Candidate {
+            value: 'x',
+            dataset,
+            description: NonZeroU32::new(9),
+        };
+        assert!(trie.append_candidate("x".to_string(), unknown).is_err());
+    }
+
+    #[test]
+    fn normalized_matches_prefer_case() {
+        let trie = trie_of(&["Gamma", "gamma", "bb_C"]);
+        let matches: Vec<&str> = trie
+            .find_normalized_matches(b"gam")
+            .into_iter()
+            .map(|(idx, _)| trie.value_str(idx))
+            .collect();
+        assert_eq!(matches, ["gamma", "Gamma"]);
+        let (idx, len) = trie.find_normalized_matches(b"bbc")[0];
+        assert_eq!((trie.value_str(idx), len), ("bb_C", 4));
+        // Separators split across labels, and mismatches inside a label
+        let trie = trie_of(&["a_b_c", "ab_d", "abe", "x-ab"]);
+        let matches: Vec<(&str, usize)> = trie
+            .find_normalized_matches(b"ab-")
+            .into_iter()
+            .map(|(idx, len)| (trie.value_str(idx), len))
+            .collect();
+        assert_eq!(matches, [("a_b_c", 3), ("ab_d", 2), ("abe", 2)]);
+        assert!(trie.find_normalized_matches(b"abx").is_empty());
+    }
+
+    #[test]
+    fn validate_detects_broken_invariants() {
+        let mut trie = trie_of(&["alpha", "alps"]);
+        trie.children.to_mut()[0].reverse();
+        trie.nodes.to_mut()[1].parent = 5;
+        assert!(trie.validate().is_err());
+
+        let mut trie = trie_of(&["alpha", "beta"]);
+        trie.children.to_mut()[0].reverse();
+        assert!(trie.validate().is_err());
+
+        // Out of range values, as in a corrupted cache, are errors rather than panics
+        let mut trie = trie_of(&["alpha", "beta"]);
+        trie.nodes.to_mut()[1].terminal = 7;
+        assert!(trie.validate().is_err());
+        let mut trie = trie_of(&["alpha", "beta"]);
+        trie.nodes.to_mut()[2].label_end = 1000;
+        assert!(trie.validate().is_err());
+        let mut trie = trie_of(&["alpha"]);
+        trie.nodes.to_mut().clear();
+        assert!(trie.validate().is_err());
+    }
 }
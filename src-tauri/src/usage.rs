@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How often and how recently a character was inserted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CharUsage {
+    pub count: u32,
+    /// Seconds since the UNIX epoch
+    pub last_used: u64,
+}
+
+/// Usage history of inserted characters, persisted as a JSON file
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Usage {
+    pub chars: BTreeMap<char, CharUsage>,
+}
+
+impl Usage {
+    /// Reads the usage history from a JSON file. A missing file is treated as an empty history
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(io::BufReader::new(file))?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Usage::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Records an insertion of the character at the given time
+    pub fn record(&mut self, ch: char, now: u64) {
+        let usage = self.chars.entry(ch).or_default();
+        usage.count = usage.count.saturating_add(1);
+        usage.last_used = now;
+    }
+
+    /// Returns the frecency of the character: the number of insertions weighted by how recent the last one was
+    ///
+    /// Characters that were never inserted score 0
+    pub fn frecency(&self, ch: char, now: u64) -> u64 {
+        const DAY: u64 = 24 * 60 * 60;
+        let Some(usage) = self.chars.get(&ch) else {
+            return 0;
+        };
+        let weight = match now.saturating_sub(usage.last_used) / DAY {
+            0..=3 => 100,
+            4..=13 => 70,
+            14..=30 => 50,
+            31..=89 => 30,
+            _ => 10,
+        };
+        usage.count as u64 * weight
+    }
+}
+
+/// Current time in seconds since the UNIX epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,208 @@
+//! Property-based tests of `Trie` against a simple model of the inserted aliases
+
+use proptest::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use unialias_core::trie::Trie;
+
+// A small alphabet, so that aliases share prefixes and labels are split often
+fn alias() -> impl Strategy<Value = String> {
+    "[ab_c]{1,8}"
+}
+
+fn entries() -> impl Strategy<Value = Vec<(String, char)>> {
+    prop::collection::vec((alias(), prop::char::range('α', 'ω')), 0..64)
+}
+
+// Inserts the entries, checking the invariants after every insert
+// Returns the trie and the model: each alias with its distinct candidates in insertion order
+fn build(entries: &[(String, char)]) -> Result<(Trie, BTreeMap<String, Vec<char>>), TestCaseError> {
+    let mut trie = Trie::new();
+    let dataset = trie.add_dataset("test");
+    let mut model: BTreeMap<String, Vec<char>> = BTreeMap::new();
+    for (alias, value) in entries {
+        let values = model.entry(alias.clone()).or_default();
+        let duplicate = values.contains(value);
+        let result = trie.append_leaf(alias.clone(), *value, dataset);
+        prop_assert_eq!(result.is_err(), duplicate);
+        if !duplicate {
+            values.push(*value);
+        }
+        if let Err(e) = trie.validate() {
+            return Err(TestCaseError::fail(format!(
+                "after inserting {:?}: {}",
+                alias, e
+            )));
+        }
+    }
+    trie.build_infix_index();
+    if let Err(e) = trie.validate() {
+        return Err(TestCaseError::fail(format!(
+            "after building the infix index: {}",
+            e
+        )));
+    }
+    Ok((trie, model))
+}
+
+// Damerau-Levenshtein distance with adjacent transpositions (optimal string alignment)
+fn distance(a: &[u8], b: &[u8]) -> usize {
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+proptest! {
+    #[test]
+    fn inserted_aliases_are_found(entries in entries()) {
+        let (trie, model) = build(&entries)?;
+        // Every inserted alias returns the model's values, in insertion order
+        for (alias, values) in &model {
+            let found: Vec<char> = trie.find_value(alias).map(|c| c.iter().map(|c| c.value).collect()).unwrap_or_default();
+            prop_assert_eq!(&found, values);
+        }
+        let found: BTreeSet<String> = trie
+            .find_infix_matches(b"a")
+            .into_iter()
+            .map(|(idx, _)| trie.value_str(idx).to_string())
+            .collect();
+        // The infix index lists every alias containing "a" after its first character
+        let expected: BTreeSet<String> = model
+            .iter()
+            .filter(|(a, values)| !values.is_empty() && a[1..].contains('a'))
+            .map(|(a, _)| a.clone())
+            .collect();
+        prop_assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn iteration_yields_exactly_the_aliases(entries in entries()) {
+        let (trie, model) = build(&entries)?;
+        let iterated: Vec<&str> = trie
+            .iter(0)
+            .filter(|&(idx, _)| trie.candidates(idx).is_some())
+            .map(|(idx, _)| trie.value_str(idx))
+            .collect();
+        // BTreeMap keys are sorted, as aliases are iterated in lexicographic order
+        let expected: Vec<&str> = model.keys().map(|a| a.as_str()).collect();
+        prop_assert_eq!(iterated, expected);
+    }
+
+    #[test]
+    fn other_strings_are_not_found(entries in entries(), query in alias()) {
+        let (trie, model) = build(&entries)?;
+        prop_assert_eq!(trie.find_value(&query).is_ok(), model.contains_key(&query));
+    }
+
+    #[test]
+    fn max_match_is_the_longest_common_prefix(entries in entries(), query in alias()) {
+        let (trie, model) = build(&entries)?;
+        let (idx, len) = trie.find_max_match(query.as_bytes());
+        let expected = model
+            .keys()
+            .map(|a| a.bytes().zip(query.bytes()).take_while(|(x, y)| x == y).count())
+            .max()
+            .unwrap_or(0);
+        prop_assert_eq!(len, expected);
+        prop_assert_eq!(&trie.value(idx)[..len], &query.as_bytes()[..len]);
+        // Every alias below the match starts with the matched part
+        for (i, _) in trie.iter(idx) {
+            prop_assert!(trie.value(i).starts_with(&query.as_bytes()[..len]));
+        }
+    }
+
+    #[test]
+    fn infix_matches_are_the_containing_aliases(entries in entries(), query in "[ab_c]{1,3}") {
+        let (trie, model) = build(&entries)?;
+        let found: BTreeSet<(String, usize)> = trie
+            .find_infix_matches(query.as_bytes())
+            .into_iter()
+            .map(|(idx, offset)| (trie.value_str(idx).to_string(), offset))
+            .collect();
+        let expected: BTreeSet<(String, usize)> = model
+            .keys()
+            .filter_map(|a| a.get(1..)?.find(&query).map(|pos| (a.clone(), pos + 1)))
+            .collect();
+        prop_assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn removed_candidates_are_not_found(entries in entries(), remove in prop::collection::vec(any::<prop::sample::Index>(), 0..16)) {
+        let (mut trie, mut model) = build(&entries)?;
+        let aliases: Vec<String> = model.keys().cloned().collect();
+        for index in remove {
+            if aliases.is_empty() {
+                break;
+            }
+            let alias = index.get(&aliases);
+            let values = model.get_mut(alias).unwrap();
+            if let Some(value) = values.pop() {
+                prop_assert!(trie.remove_candidate(alias, value, 0).is_ok());
+            } else {
+                prop_assert!(trie.remove_candidate(alias, 'α', 0).is_err());
+            }
+            prop_assert!(trie.validate().is_ok());
+        }
+        // Aliases left without candidates are gone, from the tree and from the infix index
+        for (alias, values) in &model {
+            let found: Vec<char> = trie.find_value(alias).map(|c| c.iter().map(|c| c.value).collect()).unwrap_or_default();
+            prop_assert_eq!(&found, values);
+        }
+        let found: BTreeSet<String> = trie
+            .find_infix_matches(b"a")
+            .into_iter()
+            .map(|(idx, _)| trie.value_str(idx).to_string())
+            .collect();
+        let expected: BTreeSet<String> = model
+            .iter()
+            .filter(|(a, values)| !values.is_empty() && a[1..].contains('a'))
+            .map(|(a, _)| a.clone())
+            .collect();
+        prop_assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn similar_aliases_are_within_the_distance(entries in entries(), query in "[ab_c]{1,6}", max in 1usize..3) {
+        let (trie, model) = build(&entries)?;
+        let mut found: BTreeMap<String, usize> = BTreeMap::new();
+        for (idx, len, dist) in trie.find_similar(query.as_bytes(), max, false) {
+            let aliases: Vec<usize> = if len < query.len() {
+                vec![idx]
+            } else {
+                trie.iter(idx).map(|(i, _)| i).collect()
+            };
+            for i in aliases.into_iter().filter(|&i| trie.candidates(i).is_some()) {
+                prop_assert!(found.insert(trie.value_str(i).to_string(), dist).is_none());
+            }
+        }
+        let q = query.as_bytes();
+        let expected: BTreeMap<String, usize> = model
+            .keys()
+            .filter_map(|alias| {
+                let a = alias.as_bytes();
+                let dist = if a.len() < q.len() {
+                    Some(distance(q, a)).filter(|&d| d <= max)
+                } else {
+                    (q.len()..=a.len()).map(|len| distance(q, &a[..len])).find(|&d| d <= max)
+                };
+                dist.map(|d| (alias.clone(), d))
+            })
+            .collect();
+        prop_assert_eq!(found, expected);
+    }
+}